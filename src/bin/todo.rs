@@ -0,0 +1,459 @@
+//! Manage todos from the command line.
+//!
+//! Todos are kept in a json file using the same format the web app stores in local storage.
+//! Cleared todos are archived in a second file next to it, like `todo.archive.json`, and removed
+//! ones are moved to a trash file, like `todo.trash.json`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path,PathBuf};
+use std::process;
+
+use euca_todomvc::todo::{Todo,Item,ItemId,Message,Command,Commands,Filter,QuickAdd,STORAGE_KEY,ARCHIVE_KEY,TRASH_KEY};
+use euca_todomvc::todo::schema;
+
+const DEFAULT_FILE: &str = "todo.json";
+
+const USAGE: &str = "\
+usage: todo [--file <path>] <command> [<args>]
+
+commands:
+    list [--active|--completed|--due]
+                                  list todos
+    add <text>...                 add a todo
+    subtask <id> <text>...        add a subtask to a todo
+    remove <id>                   move a todo to the trash
+    toggle <id>                   toggle whether a todo is complete
+    edit <id> <text>...           change the text of a todo
+    clear-completed               archive all completed todos
+    restore <id>                  restore an archived or removed todo
+    toggle-all                    mark all todos complete (or incomplete if they already are)
+
+options:
+    -f, --file <path>             the todo file to use (default: todo.json)
+
+cleared todos are kept in <name>.archive.json next to the todo file, and removed
+todos in <name>.trash.json
+";
+
+#[derive(Debug,PartialEq)]
+enum Action {
+    List(Filter),
+    Add(String),
+    AddSubtask(ItemId, String),
+    Remove(ItemId),
+    Toggle(ItemId),
+    Edit(ItemId, String),
+    ClearCompleted,
+    ToggleAll,
+    Restore(ItemId),
+}
+
+#[derive(Debug,PartialEq)]
+struct Args {
+    file: PathBuf,
+    action: Action,
+}
+
+fn parse_id(arg: Option<String>) -> Result<ItemId, String> {
+    let arg = arg.ok_or("missing todo id")?;
+    arg.parse().map_err(|_| format!("invalid todo id '{}'", arg))
+}
+
+fn parse_text(args: Vec<String>) -> Result<String, String> {
+    let text = args.join(" ");
+    if text.trim().is_empty() {
+        return Err("missing todo text".to_owned());
+    }
+    Ok(text)
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut file = PathBuf::from(DEFAULT_FILE);
+
+    let command = loop {
+        match args.next() {
+            Some(ref arg) if arg == "-f" || arg == "--file" => {
+                file = args.next().ok_or("missing path for --file")?.into();
+            }
+            Some(arg) => break arg,
+            None => return Err("missing command".to_owned()),
+        }
+    };
+
+    let action = match command.as_ref() {
+        "list" | "ls" => {
+            let filter = match args.next().as_deref() {
+                None => Filter::All,
+                Some("--active") => Filter::Active,
+                Some("--completed") => Filter::Completed,
+                Some("--due") => Filter::Due,
+                Some(arg) => return Err(format!("unknown list option '{}'", arg)),
+            };
+            Action::List(filter)
+        }
+        "add" => Action::Add(parse_text(args.by_ref().collect())?),
+        "subtask" => {
+            let id = parse_id(args.next())?;
+            Action::AddSubtask(id, parse_text(args.by_ref().collect())?)
+        }
+        "remove" | "rm" => Action::Remove(parse_id(args.next())?),
+        "toggle" => Action::Toggle(parse_id(args.next())?),
+        "edit" => {
+            let id = parse_id(args.next())?;
+            Action::Edit(id, parse_text(args.by_ref().collect())?)
+        }
+        "clear-completed" => Action::ClearCompleted,
+        "toggle-all" => Action::ToggleAll,
+        "restore" => Action::Restore(parse_id(args.next())?),
+        _ => return Err(format!("unknown command '{}'", command)),
+    };
+
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument '{}'", arg));
+    }
+
+    Ok(Args { file, action })
+}
+
+fn read_items(path: &Path) -> Result<Vec<Item>, String> {
+    match fs::read_to_string(path) {
+        Ok(data) => schema::decode(&data)
+            .map_err(|e| format!("error reading {}: {}", path.display(), e)),
+        // start a new list
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!("error reading {}: {}", path.display(), e)),
+    }
+}
+
+/// The file next to the todo file that holds its archived items, e.g. `todo.archive.json`.
+fn archive_path(path: &Path) -> PathBuf {
+    companion_path(path, "archive")
+}
+
+/// The file next to the todo file that holds its removed items, e.g. `todo.trash.json`.
+fn trash_path(path: &Path) -> PathBuf {
+    companion_path(path, "trash")
+}
+
+/// Insert a suffix before the extension of the todo file.
+fn companion_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(ext) => path.with_file_name(format!("{}.{}.{}", stem, suffix, ext.to_string_lossy())),
+        None => path.with_file_name(format!("{}.{}", stem, suffix)),
+    }
+}
+
+/// Process the commands generated by the model the way the web app would.
+fn process(cmds: Commands, path: &Path) -> Result<(), String> {
+    for cmd in cmds.immediate.into_iter().chain(cmds.post_render) {
+        match cmd {
+            Command::UpdateStorage(_, key, data) => {
+                // the file holds a single list, stored under the default keys
+                let file = match key.as_ref() {
+                    STORAGE_KEY => path.to_owned(),
+                    ARCHIVE_KEY => archive_path(path),
+                    TRASH_KEY => trash_path(path),
+                    _ => continue,
+                };
+                fs::write(&file, data)
+                    .map_err(|e| format!("error writing {}: {}", file.display(), e))?;
+            }
+            Command::ClearStorage(..) => {}
+            // nothing to focus, navigate, copy or transfer in a terminal
+            Command::FocusPending
+            | Command::FocusEdit
+            | Command::PushHistory(_)
+            | Command::ReplaceHistory(_)
+            | Command::PopHistory
+            | Command::SetTitle(_)
+            | Command::ScrollTo(_)
+            | Command::CopyLink(_)
+            | Command::Download(..)
+            | Command::Upload
+            => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn list(mut todo: Todo, filter: Filter) {
+    todo.filter = filter;
+
+    for item in todo.visible_items() {
+        print_item(&todo, item, 0);
+    }
+}
+
+/// Print an item and its visible subtasks, indented by depth.
+fn print_item(todo: &Todo, item: &Item, depth: usize) {
+    let time = match item.due_time {
+        Some(time) => format!(" {}", time.format("%H:%M")),
+        None => String::new(),
+    };
+
+    let due = match item.due {
+        Some(due) if item.is_overdue(todo.today()) => format!(" (overdue since {}{})", due, time),
+        Some(due) => format!(" (due {}{})", due, time),
+        None => String::new(),
+    };
+
+    let priority = if item.priority.is_none() {
+        String::new()
+    }
+    else {
+        format!(" [{}]", item.priority.name())
+    };
+
+    let recurrence = match item.recurrence {
+        Some(recurrence) => format!(" ({})", recurrence.describe().to_lowercase()),
+        None => String::new(),
+    };
+
+    let progress = match item.progress() {
+        Some((complete, total)) => format!(" ({}/{})", complete, total),
+        None => String::new(),
+    };
+
+    println!("{}[{}] {:>3} {}{}{}{}{}",
+        "    ".repeat(depth),
+        if item.is_complete { "x" } else { " " },
+        item.id,
+        item.text_with_tags(),
+        progress,
+        priority,
+        due,
+        recurrence,
+    );
+
+    for child in todo.visible_children(item) {
+        print_item(todo, child, depth + 1);
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut todo = Todo::with_items(read_items(&args.file)?)
+        .with_archive(read_items(&archive_path(&args.file))?)
+        .with_trash(read_items(&trash_path(&args.file))?);
+
+    let messages = match args.action {
+        Action::List(filter) => {
+            list(todo, filter);
+            return Ok(());
+        }
+        Action::Add(text) => vec![
+            Message::UpdatePending(text),
+            Message::AddTodo,
+        ],
+        Action::AddSubtask(id, text) => vec![Message::AddSubtask(id, text)],
+        Action::Remove(id) => vec![Message::RemoveTodo(id)],
+        Action::Toggle(id) => vec![Message::ToggleTodo(id)],
+        Action::Edit(id, text) => vec![
+            Message::EditTodo(id),
+            Message::UpdateEdit(text),
+            Message::SaveEdit,
+        ],
+        Action::ClearCompleted => vec![Message::ClearCompleted],
+        Action::ToggleAll => vec![Message::ToggleAll],
+        Action::Restore(id) if todo.trash.iter().any(|item| item.id == id) => vec![Message::RestoreTrashed(id)],
+        Action::Restore(id) => vec![Message::RestoreArchived(id)],
+    };
+
+    // messages for unknown items are silently ignored by the model, report them instead
+    match messages[0] {
+        Message::RemoveTodo(id) | Message::ToggleTodo(id) | Message::EditTodo(id) | Message::AddSubtask(id, _)
+        if todo.find(id).is_none() => {
+            return Err(format!("no todo with id {}", id));
+        }
+        Message::RestoreArchived(id) if todo.archive.iter().all(|item| item.id != id) => {
+            return Err(format!("no archived or removed todo with id {}", id));
+        }
+        // tags alone don't make a todo
+        Message::UpdatePending(ref text) if QuickAdd::parse(text, todo.today()).text.is_empty() => {
+            return Err("missing todo text".to_owned());
+        }
+        _ => {}
+    }
+
+    let mut cmds = Commands::default();
+    for msg in messages {
+        todo.update(msg, &mut cmds);
+    }
+
+    process(cmds, &args.file)
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("todo: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("todo: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse(&["list"]).unwrap().action, Action::List(Filter::All));
+        assert_eq!(parse(&["list", "--active"]).unwrap().action, Action::List(Filter::Active));
+        assert_eq!(parse(&["list", "--completed"]).unwrap().action, Action::List(Filter::Completed));
+        assert_eq!(parse(&["list", "--due"]).unwrap().action, Action::List(Filter::Due));
+        assert_eq!(parse(&["add", "buy", "milk"]).unwrap().action, Action::Add("buy milk".to_owned()));
+        assert_eq!(parse(&["subtask", "3", "buy", "oat", "milk"]).unwrap().action, Action::AddSubtask(3, "buy oat milk".to_owned()));
+        assert_eq!(parse(&["remove", "3"]).unwrap().action, Action::Remove(3));
+        assert_eq!(parse(&["toggle", "3"]).unwrap().action, Action::Toggle(3));
+        assert_eq!(parse(&["edit", "3", "new", "text"]).unwrap().action, Action::Edit(3, "new text".to_owned()));
+        assert_eq!(parse(&["clear-completed"]).unwrap().action, Action::ClearCompleted);
+        assert_eq!(parse(&["toggle-all"]).unwrap().action, Action::ToggleAll);
+        assert_eq!(parse(&["restore", "3"]).unwrap().action, Action::Restore(3));
+
+        let args = parse(&["--file", "work.json", "list"]).unwrap();
+        assert_eq!(args.file, PathBuf::from("work.json"));
+        assert_eq!(parse(&["list"]).unwrap().file, PathBuf::from(DEFAULT_FILE));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["add"]).is_err());
+        assert!(parse(&["remove"]).is_err());
+        assert!(parse(&["remove", "x"]).is_err());
+        assert!(parse(&["toggle", "1", "2"]).is_err());
+        assert!(parse(&["list", "--done"]).is_err());
+        assert!(parse(&["--file"]).is_err());
+    }
+
+    #[test]
+    fn run_against_file() {
+        let path = env::temp_dir().join(format!("todo-cli-test-{}.json", process::id()));
+        let args = |action| Args { file: path.clone(), action };
+
+        run(args(Action::Add("one".to_owned()))).unwrap();
+        run(args(Action::Add("two".to_owned()))).unwrap();
+        assert!(run(args(Action::Add("#x".to_owned()))).is_err());
+
+        let items = read_items(&path).unwrap();
+        assert_eq!(items.len(), 2);
+
+        run(args(Action::AddSubtask(items[1].id, "two.a".to_owned()))).unwrap();
+        assert!(run(args(Action::AddSubtask(999, "nope".to_owned()))).is_err());
+        let subtask = read_items(&path).unwrap()[1].children[0].id;
+        run(args(Action::Toggle(subtask))).unwrap();
+        assert!(read_items(&path).unwrap()[1].children[0].is_complete);
+
+        run(args(Action::Toggle(items[0].id))).unwrap();
+        run(args(Action::Edit(items[1].id, "  three ".to_owned()))).unwrap();
+        assert!(run(args(Action::Remove(999))).is_err());
+
+        let items = read_items(&path).unwrap();
+        assert!(items[0].is_complete);
+        assert_eq!(items[1].text, "three");
+
+        run(args(Action::ClearCompleted)).unwrap();
+        assert_eq!(read_items(&path).unwrap().len(), 1);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(archive_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn companion_paths() {
+        assert_eq!(archive_path(Path::new("todo.json")), PathBuf::from("todo.archive.json"));
+        assert_eq!(archive_path(Path::new("lists/work.json")), PathBuf::from("lists/work.archive.json"));
+        assert_eq!(archive_path(Path::new("todo")), PathBuf::from("todo.archive"));
+        assert_eq!(trash_path(Path::new("todo.json")), PathBuf::from("todo.trash.json"));
+    }
+
+    #[test]
+    fn archive_to_file() {
+        let path = env::temp_dir().join(format!("todo-cli-archive-test-{}.json", process::id()));
+        let args = |action| Args { file: path.clone(), action };
+
+        run(args(Action::Add("one".to_owned()))).unwrap();
+        run(args(Action::Add("two".to_owned()))).unwrap();
+        run(args(Action::Add("three".to_owned()))).unwrap();
+        let ids: Vec<ItemId> = read_items(&path).unwrap().iter().map(|item| item.id).collect();
+
+        run(args(Action::Toggle(ids[0]))).unwrap();
+        run(args(Action::ClearCompleted)).unwrap();
+        run(args(Action::Toggle(ids[2]))).unwrap();
+        run(args(Action::ClearCompleted)).unwrap();
+
+        // cleared items are added to the archive kept from earlier runs
+        let texts = |items: Vec<Item>| items.into_iter().map(|item| item.text).collect::<Vec<_>>();
+        assert_eq!(texts(read_items(&path).unwrap()), ["two"]);
+        assert_eq!(texts(read_items(&archive_path(&path)).unwrap()), ["one", "three"]);
+
+        // new todos don't take the ids of archived ones, so those can be restored
+        run(args(Action::Add("four".to_owned()))).unwrap();
+        let four = read_items(&path).unwrap()[1].id;
+        assert!(!ids.contains(&four));
+
+        run(args(Action::Restore(ids[0]))).unwrap();
+        assert!(run(args(Action::Restore(ids[0]))).is_err());
+        assert!(run(args(Action::Restore(four))).is_err());
+        let items = read_items(&path).unwrap();
+        assert_eq!(texts(items.clone()), ["two", "four", "one"]);
+        assert_eq!(items[2].id, ids[0]);
+        assert_eq!(texts(read_items(&archive_path(&path)).unwrap()), ["three"]);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(archive_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn trash_to_file() {
+        let path = env::temp_dir().join(format!("todo-cli-trash-test-{}.json", process::id()));
+        let args = |action| Args { file: path.clone(), action };
+
+        run(args(Action::Add("one".to_owned()))).unwrap();
+        run(args(Action::Add("two".to_owned()))).unwrap();
+        run(args(Action::Add("three".to_owned()))).unwrap();
+        let ids: Vec<ItemId> = read_items(&path).unwrap().iter().map(|item| item.id).collect();
+
+        run(args(Action::Remove(ids[1]))).unwrap();
+        run(args(Action::Remove(ids[0]))).unwrap();
+
+        // removed items are added to the trash kept from earlier runs
+        let texts = |items: Vec<Item>| items.into_iter().map(|item| item.text).collect::<Vec<_>>();
+        assert_eq!(texts(read_items(&path).unwrap()), ["three"]);
+        let trash = read_items(&trash_path(&path)).unwrap();
+        assert!(trash.iter().all(|item| item.deleted_at.is_some()));
+        assert_eq!(texts(trash), ["two", "one"]);
+
+        // new todos don't take the ids of removed ones, so those can be restored
+        run(args(Action::Remove(ids[2]))).unwrap();
+        run(args(Action::Add("four".to_owned()))).unwrap();
+        let four = read_items(&path).unwrap()[0].id;
+        assert!(!ids.contains(&four));
+
+        run(args(Action::Restore(ids[2]))).unwrap();
+        let items = read_items(&path).unwrap();
+        assert_eq!(texts(items.clone()), ["four", "three"]);
+        assert_eq!(items[1].id, ids[2]);
+        assert_eq!(items[1].deleted_at, None);
+        assert_eq!(texts(read_items(&trash_path(&path)).unwrap()), ["two", "one"]);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(trash_path(&path)).unwrap();
+    }
+}
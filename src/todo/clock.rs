@@ -0,0 +1,95 @@
+use chrono::{DateTime,Local,NaiveDate,TimeZone,Utc};
+
+/// A source for the current date and time.
+///
+/// The model never asks the system for the time directly, so tests can pin it with a
+/// [`FixedClock`].
+pub trait Clock {
+    /// The current local date.
+    fn today(&self) -> NaiveDate;
+
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock.
+#[derive(Default)]
+pub struct SystemClock {}
+
+impl Clock for SystemClock {
+    fn today(&self) -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that is stopped at a given date.
+pub struct FixedClock {
+    pub today: NaiveDate,
+}
+
+impl Clock for FixedClock {
+    fn today(&self) -> NaiveDate {
+        self.today
+    }
+
+    /// Noon on the fixed date.
+    fn now(&self) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&self.today.and_hms_opt(12, 0, 0).expect("noon is a valid time"))
+    }
+}
+
+/// Describe how long ago something happened, like "2h ago".
+///
+/// Anything more than a month ago is shown as a date instead.
+pub fn ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now.signed_duration_since(then);
+
+    if elapsed.num_minutes() < 1 {
+        // includes times in the future, from clocks that disagree
+        "just now".to_owned()
+    }
+    else if elapsed.num_hours() < 1 {
+        format!("{}m ago", elapsed.num_minutes())
+    }
+    else if elapsed.num_days() < 1 {
+        format!("{}h ago", elapsed.num_hours())
+    }
+    else if elapsed.num_days() <= 30 {
+        format!("{}d ago", elapsed.num_days())
+    }
+    else {
+        format!("on {}", then.date_naive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn relative_times() {
+        let now = FixedClock { today: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap() }.now();
+
+        let cases = [
+            (Duration::seconds(-30), "just now"),
+            (Duration::seconds(0), "just now"),
+            (Duration::seconds(59), "just now"),
+            (Duration::minutes(1), "1m ago"),
+            (Duration::minutes(59), "59m ago"),
+            (Duration::minutes(150), "2h ago"),
+            (Duration::hours(23), "23h ago"),
+            (Duration::hours(24), "1d ago"),
+            (Duration::days(30), "30d ago"),
+            (Duration::days(31), "on 2024-02-13"),
+        ];
+
+        for &(elapsed, expected) in &cases {
+            assert_eq!(ago(now - elapsed, now), expected, "{:?}", elapsed);
+        }
+    }
+}
@@ -0,0 +1,590 @@
+//! Exporting and importing todos in various formats.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use chrono::NaiveDate;
+
+use super::{Item,Priority,parse_tags};
+use super::item::is_tag;
+use super::schema;
+
+/// A format todos can be exported to and imported from.
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum Format {
+    /// The format used for storage.
+    Json,
+    /// Comma separated values with an `id,title,completed,due,priority,tags` header. Subtasks
+    /// are written as separate rows after their parent.
+    Csv,
+    /// A GitHub style Markdown checklist (`- [x] title #tag`).
+    Markdown,
+    /// The todo.txt format (http://todotxt.org).
+    TodoTxt,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Json, Format::Csv, Format::Markdown, Format::TodoTxt];
+
+    /// A short, human readable name for the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Csv => "CSV",
+            Format::Markdown => "Markdown",
+            Format::TodoTxt => "todo.txt",
+        }
+    }
+
+    /// The file name to use when exporting in this format.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Format::Json => "todos.json",
+            Format::Csv => "todos.csv",
+            Format::Markdown => "todos.md",
+            Format::TodoTxt => "todo.txt",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Csv => "text/csv",
+            Format::Markdown => "text/markdown",
+            Format::TodoTxt => "text/plain",
+        }
+    }
+
+    /// Guess the format of a file from its name.
+    pub fn from_file_name(name: &str) -> Option<Format> {
+        let name = name.to_lowercase();
+        if name.ends_with(".json") {
+            Some(Format::Json)
+        }
+        else if name.ends_with(".csv") {
+            Some(Format::Csv)
+        }
+        else if name.ends_with(".md") || name.ends_with(".markdown") {
+            Some(Format::Markdown)
+        }
+        else if name.ends_with(".txt") {
+            Some(Format::TodoTxt)
+        }
+        else {
+            None
+        }
+    }
+}
+
+#[derive(PartialEq,Debug)]
+pub struct Error {
+    line: usize,
+    message: String,
+}
+
+impl Error {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Error {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+        else {
+            write!(f, "{}", self.message)
+        }
+    }
+}
+
+/// Export items in the given format.
+pub fn export(items: &[Item], format: Format) -> String {
+    match format {
+        Format::Json => schema::encode(items),
+        Format::Csv => export_csv(items),
+        Format::Markdown => export_markdown(items),
+        Format::TodoTxt => export_todo_txt(items),
+    }
+}
+
+/// Import items from data in the given format.
+///
+/// Imported items don't have ids (or keep the ones they were exported with), callers should
+/// assign fresh ones.
+pub fn import(data: &str, format: Format) -> Result<Vec<Item>, Error> {
+    match format {
+        Format::Json => schema::decode(data)
+            .map_err(|e| Error::new(0, e.to_string())),
+        Format::Csv => import_csv(data),
+        Format::Markdown => import_markdown(data),
+        Format::TodoTxt => import_todo_txt(data),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    }
+    else {
+        field.to_owned()
+    }
+}
+
+fn export_csv(items: &[Item]) -> String {
+    let mut out = String::from("id,title,completed,due,priority,tags\n");
+    for item in flatten(items) {
+        out.push_str(&format!("{},{},{},{},{},{}\n",
+            item.id,
+            csv_field(&item.text),
+            item.is_complete,
+            item.due.map(|due| due.to_string()).unwrap_or_default(),
+            if item.priority.is_none() { "" } else { item.priority.name() },
+            item.tags.iter().cloned().collect::<Vec<_>>().join(" "),
+        ));
+    }
+    out
+}
+
+/// Split csv data into records of fields, handling quoted fields.
+fn parse_csv(data: &str) -> Result<Vec<(usize, Vec<String>)>, Error> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut in_quotes = false;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                }
+                else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push(c);
+            }
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(Error::new(record_line, "unterminated quoted field"));
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    // skip blank lines
+    records.retain(|(_, record)| !(record.len() == 1 && record[0].trim().is_empty()));
+
+    Ok(records)
+}
+
+fn import_csv(data: &str) -> Result<Vec<Item>, Error> {
+    let mut records = parse_csv(data)?.into_iter();
+
+    let header = match records.next() {
+        Some((_, header)) => header,
+        None => return Ok(vec![]),
+    };
+
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let title = column("title").ok_or_else(|| Error::new(1, "missing 'title' column"))?;
+    let completed = column("completed");
+    let id = column("id");
+    let due = column("due");
+    let priority = column("priority");
+    let tags = column("tags");
+
+    records
+        .map(|(line, record)| {
+            let text = record.get(title)
+                .ok_or_else(|| Error::new(line, "missing title"))?;
+
+            let is_complete = match completed.and_then(|i| record.get(i)).map(|s| s.trim()) {
+                None | Some("") | Some("false") | Some("0") => false,
+                Some("true") | Some("1") | Some("x") => true,
+                Some(value) => return Err(Error::new(line, format!("invalid completed value '{}'", value))),
+            };
+
+            let due = match due.and_then(|i| record.get(i)).map(|s| s.trim()) {
+                None | Some("") => None,
+                Some(value) => Some(parse_date(value)
+                    .ok_or_else(|| Error::new(line, format!("invalid due date '{}'", value)))?),
+            };
+
+            let priority = match priority.and_then(|i| record.get(i)).map(|s| s.trim()) {
+                None | Some("") => Priority::None,
+                Some(value) => Priority::from_name(&value.to_lowercase())
+                    .ok_or_else(|| Error::new(line, format!("invalid priority '{}'", value)))?,
+            };
+
+            let tags = tags.and_then(|i| record.get(i))
+                .map(|s| s.split_whitespace()
+                    .map(|tag| {
+                        let tag = tag.trim_start_matches('#').to_ascii_lowercase();
+                        if is_tag(&tag) {
+                            Ok(tag)
+                        }
+                        else {
+                            Err(Error::new(line, format!("invalid tag '{}'", tag)))
+                        }
+                    })
+                    .collect::<Result<_, _>>()
+                )
+                .transpose()?
+                .unwrap_or_default();
+
+            Ok(Item {
+                id: id.and_then(|i| record.get(i))
+                    .and_then(|id| id.trim().parse().ok())
+                    .unwrap_or(0),
+                text: text.to_owned(),
+                is_complete,
+                due,
+                priority,
+                tags,
+                .. Item::default()
+            })
+        })
+        .collect()
+}
+
+/// Flatten a tree of items, parents before their subtasks.
+///
+/// Used for formats that have no way to represent subtasks.
+fn flatten(items: &[Item]) -> Vec<&Item> {
+    items.iter()
+        .flat_map(|item| std::iter::once(item).chain(flatten(&item.children)))
+        .collect()
+}
+
+/// Subtasks are written as nested checklists.
+fn export_markdown(items: &[Item]) -> String {
+    fn export(out: &mut String, items: &[Item], depth: usize) {
+        for item in items {
+            out.push_str(&format!("{}- [{}] {}\n",
+                "  ".repeat(depth),
+                if item.is_complete { "x" } else { " " },
+                item.text_with_tags().replace('\n', " "),
+            ));
+            export(out, &item.children, depth + 1);
+        }
+    }
+
+    let mut out = String::new();
+    export(&mut out, items, 0);
+    out
+}
+
+fn import_markdown(data: &str) -> Result<Vec<Item>, Error> {
+    // the items still open to subtasks, with their indentation
+    let mut stack: Vec<(usize, Item)> = vec![];
+    let mut items = vec![];
+
+    fn close(stack: &mut Vec<(usize, Item)>, items: &mut Vec<Item>) {
+        if let Some((_, item)) = stack.pop() {
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(item),
+                None => items.push(item),
+            }
+        }
+    }
+
+    let checklist = data.lines()
+        .filter_map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim_start();
+            let line = line.strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .or_else(|| line.strip_prefix("+ "))?;
+
+            let (is_complete, text) = if let Some(text) = line.strip_prefix("[ ]") {
+                (false, text)
+            }
+            else if let Some(text) = line.strip_prefix("[x]").or_else(|| line.strip_prefix("[X]")) {
+                (true, text)
+            }
+            else {
+                // not a checklist item
+                return None;
+            };
+
+            let (text, tags) = parse_tags(text);
+            Some((indent, Item {
+                text,
+                is_complete,
+                tags,
+                .. Item::default()
+            }))
+        })
+        .filter(|(_, item)| !item.text.is_empty() || !item.tags.is_empty());
+
+    for (indent, item) in checklist {
+        while matches!(stack.last(), Some(&(open, _)) if open >= indent) {
+            close(&mut stack, &mut items);
+        }
+        stack.push((indent, item));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut items);
+    }
+
+    Ok(items)
+}
+
+/// The todo.txt priority letters, todo.txt allows `A` through `Z` but only the first three are
+/// used.
+const TODO_TXT_PRIORITIES: [(Priority, &str); 3] = [
+    (Priority::High, "(A) "),
+    (Priority::Medium, "(B) "),
+    (Priority::Low, "(C) "),
+];
+
+/// Tags are written as todo.txt `+project` tags. Subtasks are written as separate tasks after
+/// their parent.
+///
+/// Note that todo.txt has no way to escape the `x ` completion marker, so incomplete items whose
+/// text starts with it will be imported as complete.
+fn export_todo_txt(items: &[Item]) -> String {
+    flatten(items).into_iter()
+        .map(|item| format!("{}{}{}{}{}\n",
+            if item.is_complete { "x " } else { "" },
+            TODO_TXT_PRIORITIES.iter()
+                .find(|(priority, _)| *priority == item.priority)
+                .map_or("", |(_, prefix)| prefix),
+            item.text.replace('\n', " "),
+            item.tags.iter().map(|tag| format!(" +{}", tag)).collect::<String>(),
+            item.due.map(|due| format!(" due:{}", due)).unwrap_or_default(),
+        ))
+        .collect()
+}
+
+fn import_todo_txt(data: &str) -> Result<Vec<Item>, Error> {
+    Ok(data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (is_complete, text) = match line.strip_prefix("x ") {
+                Some(text) => (true, strip_todo_txt_date(text)),
+                None => (false, line),
+            };
+
+            // any other priority letters are treated as low priority
+            let (priority, text) = match TODO_TXT_PRIORITIES.iter().find(|(_, prefix)| text.starts_with(prefix)) {
+                Some(&(priority, prefix)) => (priority, &text[prefix.len()..]),
+                None if is_todo_txt_priority(text) => (Priority::Low, &text[4..]),
+                None => (Priority::None, text),
+            };
+
+            // pull out the `due:` and `+project` tags
+            let mut due = None;
+            let mut tags = BTreeSet::new();
+            let text = text.split(' ')
+                .filter(|word| {
+                    if let Some(date) = word.strip_prefix("due:").and_then(parse_date) {
+                        due = Some(date);
+                        return false;
+                    }
+
+                    match word.strip_prefix('+').map(str::to_ascii_lowercase) {
+                        Some(tag) if is_tag(&tag) => {
+                            tags.insert(tag);
+                            false
+                        }
+                        _ => true,
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            Item {
+                text,
+                is_complete,
+                due,
+                priority,
+                tags,
+                .. Item::default()
+            }
+        })
+        .collect())
+}
+
+fn is_todo_txt_priority(text: &str) -> bool {
+    let b = text.as_bytes();
+    b.len() >= 4 && b[0] == b'(' && b[1].is_ascii_uppercase() && b[2] == b')' && b[3] == b' '
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+/// Remove the completion (and creation) dates todo.txt puts before the text of completed items.
+fn strip_todo_txt_date(mut text: &str) -> &str {
+    fn is_date(s: &str) -> bool {
+        let b = s.as_bytes();
+        b.len() == 10
+            && b[4] == b'-' && b[7] == b'-'
+            && b.iter().enumerate().all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+    }
+
+    for _ in 0..2 {
+        match text.split_once(' ') {
+            Some((date, rest)) if is_date(date) => text = rest,
+            _ => break,
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<Item> {
+        vec![
+            Item {
+                id: 1,
+                text: "plain".to_owned(),
+                .. Item::default()
+            },
+            Item {
+                id: 2,
+                text: "done, with \"quotes\"".to_owned(),
+                is_complete: true,
+                .. Item::default()
+            },
+            Item {
+                id: 3,
+                text: "third".to_owned(),
+                due: NaiveDate::from_ymd_opt(2020, 3, 15),
+                priority: Priority::High,
+                .. Item::default()
+            },
+            Item {
+                id: 4,
+                text: "fourth".to_owned(),
+                priority: Priority::Low,
+                tags: vec!["home".to_owned(), "q3".to_owned()].into_iter().collect(),
+                .. Item::default()
+            },
+        ]
+    }
+
+    fn summary(items: &[Item]) -> Vec<(&str, bool)> {
+        items.iter().map(|item| (item.text.as_ref(), item.is_complete)).collect()
+    }
+
+    fn due_dates(items: &[Item]) -> Vec<Option<NaiveDate>> {
+        items.iter().map(|item| item.due).collect()
+    }
+
+    fn priorities(items: &[Item]) -> Vec<Priority> {
+        items.iter().map(|item| item.priority).collect()
+    }
+
+    fn tags(items: &[Item]) -> Vec<Vec<&str>> {
+        items.iter().map(|item| item.tags.iter().map(String::as_str).collect()).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let items = items();
+
+        for &format in &Format::ALL {
+            let imported = import(&export(&items, format), format).unwrap();
+            assert_eq!(summary(&imported), summary(&items), "round trip through {}", format.name());
+            assert_eq!(tags(&imported), tags(&items), "tags through {}", format.name());
+
+            // markdown checklists have no place for due dates or priorities
+            if format != Format::Markdown {
+                assert_eq!(due_dates(&imported), due_dates(&items), "due dates through {}", format.name());
+                assert_eq!(priorities(&imported), priorities(&items), "priorities through {}", format.name());
+            }
+        }
+    }
+
+    #[test]
+    fn csv_keeps_ids() {
+        let imported = import(&export(&items(), Format::Csv), Format::Csv).unwrap();
+        assert_eq!(imported.iter().map(|item| item.id).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn csv_multiline_and_errors() {
+        let imported = import("title,completed\n\"two\nlines\",true\nother,\n", Format::Csv).unwrap();
+        assert_eq!(summary(&imported), vec![("two\nlines", true), ("other", false)]);
+
+        assert_eq!(
+            import("title,completed\nfoo,maybe\n", Format::Csv).unwrap_err(),
+            Error::new(2, "invalid completed value 'maybe'"),
+        );
+        assert!(import("title,priority\nfoo,urgent\n", Format::Csv).is_err());
+        assert!(import("title,tags\nfoo,a+b\n", Format::Csv).is_err());
+        assert!(import("id,text\n1,foo\n", Format::Csv).is_err());
+        assert!(import("title\n\"open", Format::Csv).is_err());
+    }
+
+    #[test]
+    fn markdown_ignores_other_lines() {
+        let imported = import("# Todo\n\n- [x] one\n* [ ] two #work\n- not a task\nsome text\n- [X] three", Format::Markdown).unwrap();
+        assert_eq!(summary(&imported), vec![("one", true), ("two", false), ("three", true)]);
+        assert_eq!(tags(&imported), vec![vec![], vec!["work"], vec![]]);
+    }
+
+    #[test]
+    fn markdown_subtasks() {
+        let imported = import("- [ ] one\n  - [x] one.a\n    - [ ] one.a.i\n  - [ ] one.b\n- [ ] two\n", Format::Markdown).unwrap();
+        assert_eq!(summary(&imported), vec![("one", false), ("two", false)]);
+        assert_eq!(summary(&imported[0].children), vec![("one.a", true), ("one.b", false)]);
+        assert_eq!(summary(&imported[0].children[0].children), vec![("one.a.i", false)]);
+
+        assert_eq!(import(&export(&imported, Format::Markdown), Format::Markdown).unwrap(), imported);
+
+        // other formats flatten subtasks
+        let flattened = import(&export(&imported, Format::Csv), Format::Csv).unwrap();
+        assert_eq!(flattened.len(), 5);
+        assert_eq!(flattened[2].text, "one.a.i");
+    }
+
+    #[test]
+    fn todo_txt_completion_dates() {
+        let imported = import("x 2020-01-02 2020-01-01 one\n(A) two +Work due:2020-03-15 @phone\n\nx three due:soon\n(D) four\n", Format::TodoTxt).unwrap();
+        assert_eq!(summary(&imported), vec![("one", true), ("two @phone", false), ("three due:soon", true), ("four", false)]);
+        assert_eq!(due_dates(&imported), vec![None, NaiveDate::from_ymd_opt(2020, 3, 15), None, None]);
+        assert_eq!(priorities(&imported), vec![Priority::None, Priority::High, Priority::None, Priority::Low]);
+        assert_eq!(tags(&imported), vec![vec![], vec!["work"], vec![], vec![]]);
+    }
+
+    #[test]
+    fn format_from_file_name() {
+        assert_eq!(Format::from_file_name("todos.JSON"), Some(Format::Json));
+        assert_eq!(Format::from_file_name("todos.csv"), Some(Format::Csv));
+        assert_eq!(Format::from_file_name("README.md"), Some(Format::Markdown));
+        assert_eq!(Format::from_file_name("todo.txt"), Some(Format::TodoTxt));
+        assert_eq!(Format::from_file_name("todo"), None);
+    }
+}
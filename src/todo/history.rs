@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+use super::HISTORY_LIMIT;
+
+/// Undo/redo history of the current list.
+///
+/// The model records a `Snapshot` of the items, archive and trash whenever one of them is
+/// persisted (see `Message::ItemsChanged`, `ArchiveChanged` and `TrashChanged`), so any change
+/// that gets saved can be undone.
+#[derive(Default)]
+pub struct History<T> {
+    /// The state as it was last saved.
+    saved: T,
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+}
+
+impl<T: Clone + PartialEq + Default> History<T> {
+    pub fn with_saved(saved: T) -> Self {
+        History {
+            saved,
+            .. History::default()
+        }
+    }
+
+    /// Record the state that is about to be saved. Returns false if nothing changed.
+    pub fn record(&mut self, state: &T) -> bool {
+        if self.saved == *state {
+            return false;
+        }
+
+        let previous = std::mem::replace(&mut self.saved, state.clone());
+        self.undo.push_back(previous);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+
+        true
+    }
+
+    /// Step back in history, returning the state to restore.
+    pub fn undo(&mut self) -> Option<T> {
+        let state = self.undo.pop_back()?;
+        let current = std::mem::replace(&mut self.saved, state.clone());
+        self.redo.push(current);
+        Some(state)
+    }
+
+    /// Step forward in history, returning the state to restore.
+    pub fn redo(&mut self) -> Option<T> {
+        let state = self.redo.pop()?;
+        let current = std::mem::replace(&mut self.saved, state.clone());
+        self.undo.push_back(current);
+        Some(state)
+    }
+}
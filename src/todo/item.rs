@@ -0,0 +1,290 @@
+use std::collections::BTreeSet;
+use serde::{Serialize,Deserialize};
+use chrono::{DateTime,NaiveDate,NaiveTime,Utc};
+
+use super::recurrence::Recurrence;
+use super::clock;
+
+/// A persistent, unique identifier for an item.
+pub type ItemId = u64;
+
+#[derive(Default,Clone,PartialEq,Debug,Serialize,Deserialize)]
+pub struct Item {
+    #[serde(default)]
+    pub id: ItemId,
+    #[serde(rename = "title")]
+    pub text: String,
+    #[serde(rename = "completed")]
+    pub is_complete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// The time of day the item is due, only set along with a due date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_time: Option<NaiveTime>,
+    #[serde(default, skip_serializing_if = "Priority::is_none")]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// A rule for adding a new copy of this item once it is completed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// When the item was added, unknown for items from before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the item was last completed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the item was moved to the trash, `None` for items that aren't in it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Subtasks of this item.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Item>,
+}
+
+/// Whether a name can be used as a tag. Tags are limited to lowercase ascii letters, digits,
+/// `-` and `_` so they can be used in urls as is.
+pub fn is_tag(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Pull `#tag` words out of some text. Returns the text without the tags (with whitespace between
+/// the remaining words collapsed) and the tags, lowercased.
+pub fn parse_tags(text: &str) -> (String, BTreeSet<String>) {
+    let mut tags = BTreeSet::new();
+    let text = text.split_whitespace()
+        .filter(|word| {
+            match word.strip_prefix('#').map(str::to_ascii_lowercase) {
+                Some(tag) if is_tag(&tag) => {
+                    tags.insert(tag);
+                    false
+                }
+                _ => true,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (text, tags)
+}
+
+/// Split text pasted as a list into the texts of separate items. Markdown bullets (`- `, `* `)
+/// and checkboxes (`[ ]`, `[x]`) in front of the text are dropped, as are blank lines.
+pub fn parse_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            let line = match line.strip_prefix(&['-', '*'][..]) {
+                Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest.trim_start(),
+                _ => line,
+            };
+            let line = ["[ ]", "[x]", "[X]"].iter()
+                .find_map(|checkbox| line.strip_prefix(checkbox))
+                .unwrap_or(line);
+            line.trim().to_owned()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// How important an item is.
+#[derive(Default,PartialEq,Eq,PartialOrd,Ord,Clone,Copy,Debug,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn is_none(&self) -> bool {
+        *self == Priority::None
+    }
+
+    /// The next priority when cycling through them, wrapping back around to `None`.
+    pub fn next(self) -> Self {
+        match self {
+            Priority::None => Priority::Low,
+            Priority::Low => Priority::Medium,
+            Priority::Medium => Priority::High,
+            Priority::High => Priority::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Priority::None),
+            "low" => Some(Priority::Low),
+            "medium" => Some(Priority::Medium),
+            "high" => Some(Priority::High),
+            _ => None,
+        }
+    }
+}
+
+impl Item {
+    /// The text of the item with its tags appended, the way they would be entered.
+    pub fn text_with_tags(&self) -> String {
+        let mut text = self.text.clone();
+        for tag in &self.tags {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push('#');
+            text.push_str(tag);
+        }
+        text
+    }
+
+    /// How many of the direct children of this item are complete, and how many there are.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        if self.children.is_empty() {
+            return None;
+        }
+
+        let complete = self.children.iter().filter(|child| child.is_complete).count();
+        Some((complete, self.children.len()))
+    }
+
+    /// Check if this item or any of its descendants match a predicate.
+    pub fn any(&self, f: &impl Fn(&Item) -> bool) -> bool {
+        f(self) || self.children.iter().any(|child| child.any(f))
+    }
+
+    /// Mark this item complete or incomplete, keeping track of when it was completed.
+    pub fn set_complete(&mut self, is_complete: bool, now: DateTime<Utc>) {
+        if is_complete != self.is_complete {
+            self.is_complete = is_complete;
+            self.completed_at = if is_complete { Some(now) } else { None };
+        }
+    }
+
+    /// When this item was added and completed, relative to the given time. Empty if neither is
+    /// known.
+    pub fn activity(&self, now: DateTime<Utc>) -> String {
+        let created = self.created_at.map(|t| format!("Added {}", clock::ago(t, now)));
+        let completed = self.completed_at
+            .filter(|_| self.is_complete)
+            .map(|t| format!("Completed {}", clock::ago(t, now)));
+
+        created.into_iter().chain(completed).collect::<Vec<_>>().join(" · ")
+    }
+
+    /// Check if this item is incomplete and was due before the given day.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.is_complete && matches!(self.due, Some(due) if due < today)
+    }
+}
+
+/// Find an item anywhere in a tree of items.
+pub(crate) fn find(items: &[Item], id: ItemId) -> Option<&Item> {
+    items.iter().find_map(|item| {
+        if item.id == id {
+            Some(item)
+        }
+        else {
+            find(&item.children, id)
+        }
+    })
+}
+
+pub(crate) fn find_mut(items: &mut [Item], id: ItemId) -> Option<&mut Item> {
+    items.iter_mut().find_map(|item| {
+        if item.id == id {
+            Some(item)
+        }
+        else {
+            find_mut(&mut item.children, id)
+        }
+    })
+}
+
+/// Mark an item (and, if `recursive`, its subtasks) complete or incomplete, collecting the ids
+/// of recurring items that are newly completed.
+pub(crate) fn mark_complete(
+    item: &mut Item,
+    is_complete: bool,
+    recursive: bool,
+    now: DateTime<Utc>,
+    recurring: &mut Vec<ItemId>,
+) {
+    if is_complete && !item.is_complete && item.recurrence.is_some() {
+        recurring.push(item.id);
+    }
+    item.set_complete(is_complete, now);
+    if recursive {
+        for child in &mut item.children {
+            mark_complete(child, is_complete, true, now, recurring);
+        }
+    }
+}
+
+/// Find the list of siblings an item is in, anywhere in a tree of items.
+pub(crate) fn siblings_mut(items: &mut Vec<Item>, id: ItemId) -> Option<&mut Vec<Item>> {
+    if items.iter().any(|item| item.id == id) {
+        return Some(items);
+    }
+
+    items.iter_mut().find_map(|item| siblings_mut(&mut item.children, id))
+}
+
+/// Remove an item from anywhere in a tree of items.
+pub(crate) fn remove(items: &mut Vec<Item>, id: ItemId) -> Option<Item> {
+    if let Some(i) = items.iter().position(|item| item.id == id) {
+        return Some(items.remove(i));
+    }
+
+    items.iter_mut().find_map(|item| remove(&mut item.children, id))
+}
+
+/// Remove items matching a predicate from a tree of items, returning the removed items.
+/// Children of removed items are removed along with them.
+pub(crate) fn remove_matching(items: &mut Vec<Item>, f: &impl Fn(&Item) -> bool) -> Vec<Item> {
+    let (mut removed, kept) = std::mem::take(items).into_iter().partition(|item| f(item));
+    *items = kept;
+
+    for item in items.iter_mut() {
+        removed.extend(remove_matching(&mut item.children, f));
+    }
+    removed
+}
+
+/// The lowest id greater than that of every item in a tree of items.
+pub(crate) fn next_free_id(items: &[Item]) -> ItemId {
+    items.iter()
+        .map(|item| (item.id + 1).max(next_free_id(&item.children)))
+        .max()
+        .unwrap_or(1)
+}
+
+/// Assign ids to items that don't have one (or have a duplicate one), as is the case for data
+/// stored before items had ids.
+pub(crate) fn assign_missing_ids(items: &mut [Item]) {
+    use std::collections::HashSet;
+
+    fn assign(items: &mut [Item], seen: &mut HashSet<ItemId>, next_id: &mut ItemId) {
+        for item in items.iter_mut() {
+            if item.id == 0 || !seen.insert(item.id) {
+                item.id = *next_id;
+                seen.insert(*next_id);
+                *next_id += 1;
+            }
+            assign(&mut item.children, seen, next_id);
+        }
+    }
+
+    let mut next_id = next_free_id(items);
+    assign(items, &mut HashSet::new(), &mut next_id);
+}
@@ -0,0 +1,159 @@
+//! Named todo lists, each persisted under its own storage key.
+
+use log::error;
+use serde::{Serialize,Deserialize};
+
+use super::{STORAGE_KEY,BACKUP_KEY,ARCHIVE_KEY,TRASH_KEY};
+use super::storage::TodoStore;
+
+/// The key the names and storage keys of all the lists are stored under.
+pub const LISTS_KEY: &str = "todo-euca-lists";
+/// The name of the list that exists before any others are created.
+pub const DEFAULT_NAME: &str = "Todos";
+
+#[derive(Clone,PartialEq,Debug,Serialize,Deserialize)]
+pub struct TodoList {
+    pub name: String,
+    /// The storage key the items of this list are stored under.
+    pub key: String,
+}
+
+impl Default for TodoList {
+    /// The first list, stored where items were kept before there were multiple lists.
+    fn default() -> Self {
+        TodoList {
+            name: DEFAULT_NAME.to_owned(),
+            key: STORAGE_KEY.to_owned(),
+        }
+    }
+}
+
+/// Check if a storage key is the given prefix, optionally followed by `/<n>` as used for lists
+/// other than the first.
+fn is_keyed(key: &str, prefix: &str) -> bool {
+    key == prefix
+        || key.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .and_then(|n| n.parse::<u64>().ok())
+            .is_some()
+}
+
+/// Check if a storage key holds the items of a list.
+pub fn is_list_key(key: &str) -> bool {
+    is_keyed(key, STORAGE_KEY)
+}
+
+/// Check if a storage key holds the archived items of a list.
+pub fn is_archive_key(key: &str) -> bool {
+    is_keyed(key, ARCHIVE_KEY)
+}
+
+/// Check if a storage key holds the removed items of a list.
+pub fn is_trash_key(key: &str) -> bool {
+    is_keyed(key, TRASH_KEY)
+}
+
+/// The key unreadable data stored under the given key is moved to.
+pub fn backup_key(key: &str) -> String {
+    key.replacen(STORAGE_KEY, BACKUP_KEY, 1)
+}
+
+/// The key the archived items of the list stored under the given key are kept under.
+pub fn archive_key(key: &str) -> String {
+    key.replacen(STORAGE_KEY, ARCHIVE_KEY, 1)
+}
+
+/// The key the removed items of the list stored under the given key are kept under.
+pub fn trash_key(key: &str) -> String {
+    key.replacen(STORAGE_KEY, TRASH_KEY, 1)
+}
+
+/// Pick a storage key for a new list that isn't used by any of the given lists.
+pub fn new_key(lists: &[TodoList]) -> String {
+    let n = lists.iter()
+        .filter_map(|list| list.key.strip_prefix(STORAGE_KEY)?.strip_prefix('/')?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+
+    format!("{}/{}", STORAGE_KEY, n + 1)
+}
+
+/// Clean up a list name entered by the user, returning `None` if it can't be used.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        None
+    }
+    else {
+        Some(name)
+    }
+}
+
+pub fn encode(lists: &[TodoList]) -> String {
+    serde_json::to_string(lists).expect("error serializing lists")
+}
+
+pub fn decode(data: &str) -> Result<Vec<TodoList>, serde_json::Error> {
+    serde_json::from_str(data)
+}
+
+/// Read the lists from storage, falling back to just the default list.
+pub fn read_lists(store: &dyn TodoStore) -> Vec<TodoList> {
+    let lists = match store.load(LISTS_KEY) {
+        Some(data) => decode(&data).unwrap_or_else(|e| {
+            error!("error reading lists: {}", e);
+            vec![]
+        }),
+        None => vec![],
+    };
+
+    if lists.is_empty() {
+        vec![TodoList::default()]
+    }
+    else {
+        lists
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let mut lists = vec![TodoList::default()];
+        assert_eq!(new_key(&lists), "todo-euca/1");
+
+        lists.push(TodoList {
+            name: "work".to_owned(),
+            key: "todo-euca/3".to_owned(),
+        });
+        assert_eq!(new_key(&lists), "todo-euca/4");
+
+        assert!(is_list_key("todo-euca"));
+        assert!(is_list_key("todo-euca/3"));
+        assert!(!is_list_key(LISTS_KEY));
+        assert!(!is_list_key("todo-euca-backup/3"));
+
+        assert_eq!(backup_key("todo-euca"), BACKUP_KEY);
+        assert_eq!(backup_key("todo-euca/3"), "todo-euca-backup/3");
+
+        assert_eq!(archive_key("todo-euca"), ARCHIVE_KEY);
+        assert_eq!(archive_key("todo-euca/3"), "todo-euca-archive/3");
+        assert!(is_archive_key("todo-euca-archive/3"));
+        assert!(!is_archive_key("todo-euca/3"));
+
+        assert_eq!(trash_key("todo-euca"), TRASH_KEY);
+        assert_eq!(trash_key("todo-euca/3"), "todo-euca-trash/3");
+        assert!(is_trash_key("todo-euca-trash/3"));
+        assert!(!is_trash_key("todo-euca-archive/3"));
+        assert!(!is_list_key("todo-euca-trash"));
+        assert!(!is_list_key("todo-euca-archive"));
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(normalize_name("  sprint   12 ").as_deref(), Some("sprint 12"));
+        assert_eq!(normalize_name("  "), None);
+    }
+}
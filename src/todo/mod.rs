@@ -0,0 +1,2997 @@
+//! The platform independent todo model.
+//!
+//! Everything needed to drive a todo list lives here, free of any web dependencies, so that
+//! different front-ends can share the same state machine.
+
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::rc::Rc;
+use log::error;
+use chrono::{DateTime,Local,NaiveDate,NaiveTime,Utc};
+
+pub mod storage;
+pub mod schema;
+pub mod export;
+pub mod clock;
+pub mod route;
+pub mod lists;
+pub mod search;
+pub mod recurrence;
+pub mod quick_add;
+pub mod settings;
+mod history;
+mod item;
+
+pub use item::{Item,ItemId,Priority,parse_tags,parse_lines};
+pub use route::AppRoute;
+pub use recurrence::Recurrence;
+pub use quick_add::QuickAdd;
+use history::History;
+use export::Format;
+use clock::{Clock,SystemClock};
+use storage::{TodoStore,MemoryStore};
+use lists::{TodoList,LISTS_KEY};
+use settings::{Settings,SETTINGS_KEY};
+
+/// The key the items of the first list are stored under.
+pub const STORAGE_KEY: &str = "todo-euca";
+/// Where stored data we couldn't read is kept so it isn't lost when the list is next saved.
+pub const BACKUP_KEY: &str = "todo-euca-backup";
+/// The key the archived items of the first list are stored under.
+pub const ARCHIVE_KEY: &str = "todo-euca-archive";
+/// The key the removed items of the first list are stored under.
+pub const TRASH_KEY: &str = "todo-euca-trash";
+/// How many changes can be undone.
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum Filter {
+    All,
+    Active,
+    Completed,
+    /// Incomplete items due today or earlier.
+    Due,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::All
+    }
+}
+
+impl Filter {
+    pub const ALL: [Filter; 4] = [
+        Filter::All,
+        Filter::Active,
+        Filter::Completed,
+        Filter::Due,
+    ];
+
+    /// The name used for this filter in urls.
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::All => "all",
+            Filter::Active => "active",
+            Filter::Completed => "completed",
+            Filter::Due => "due",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Filter::ALL.iter()
+            .find(|filter| filter.name() == name)
+            .copied()
+    }
+
+    /// Check if the given item should be shown with this filter.
+    ///
+    /// Items are also shown if any of their subtasks match, so a partially finished item shows up
+    /// as both active and completed (with only the matching subtasks under it).
+    pub fn matches(&self, item: &Item, today: NaiveDate) -> bool {
+        item.any(&|item| self.matches_item(item, today))
+    }
+
+    fn matches_item(&self, item: &Item, today: NaiveDate) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Active => !item.is_complete,
+            Filter::Completed => item.is_complete,
+            Filter::Due => !item.is_complete && matches!(item.due, Some(due) if due <= today),
+        }
+    }
+}
+
+/// Which items are shown.
+#[derive(PartialEq,Clone,Copy,Debug,Default)]
+pub enum View {
+    /// The items of the current list.
+    #[default]
+    Items,
+    /// Completed items cleared from the current list.
+    Archive,
+    /// Items removed from the current list.
+    Trash,
+}
+
+/// The order items are shown in.
+#[derive(Default,PartialEq,Clone,Copy,Debug)]
+pub enum SortMode {
+    /// The order the items were added in.
+    #[default]
+    Insertion,
+    /// Highest priority first.
+    Priority,
+    Alphabetical,
+    /// Incomplete items first.
+    Completion,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 4] = [
+        SortMode::Insertion,
+        SortMode::Priority,
+        SortMode::Alphabetical,
+        SortMode::Completion,
+    ];
+
+    /// The name used for this sort mode in urls.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Insertion => "insertion",
+            SortMode::Priority => "priority",
+            SortMode::Alphabetical => "alphabetical",
+            SortMode::Completion => "completion",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SortMode::ALL.iter()
+            .find(|sort| sort.name() == name)
+            .copied()
+    }
+
+    /// Sort items in place. The sort is stable, so items that compare equal keep their order.
+    pub fn sort(self, items: &mut [&Item]) {
+        match self {
+            SortMode::Insertion => {}
+            SortMode::Priority => items.sort_by_key(|item| Reverse(item.priority)),
+            SortMode::Alphabetical => items.sort_by_cached_key(|item| item.text.to_lowercase()),
+            SortMode::Completion => items.sort_by_key(|item| item.is_complete),
+        }
+    }
+}
+
+pub struct Todo {
+    pub pending_item: String,
+    pub items: Vec<Item>,
+    /// Completed items cleared from the current list, in the order they were archived.
+    pub archive: Vec<Item>,
+    /// Items removed from the current list, in the order they were removed.
+    pub trash: Vec<Item>,
+    pub view: View,
+    pub pending_edit: Option<(ItemId, String)>,
+    pub filter: Filter,
+    pub sort: SortMode,
+    /// Only show items with this tag.
+    pub tag: Option<String>,
+    /// Only show items matching this search query.
+    pub search: String,
+    pub settings: Settings,
+    /// The item selected for keyboard control.
+    pub cursor: Option<ItemId>,
+    pub lists: Vec<TodoList>,
+    /// The index in `lists` of the list `items` belong to.
+    current_list: usize,
+    next_id: ItemId,
+    store: Rc<dyn TodoStore>,
+    history: History<Snapshot>,
+    pub toast: Option<Toast>,
+    clock: Rc<dyn Clock>,
+    /// The route of the current history entry.
+    location: AppRoute,
+    /// The route before the edit in progress added a history entry of its own.
+    edit_entry: Option<AppRoute>,
+}
+
+impl Default for Todo {
+    fn default() -> Self {
+        Todo {
+            pending_item: String::default(),
+            items: vec![],
+            archive: vec![],
+            trash: vec![],
+            view: View::default(),
+            pending_edit: None,
+            filter: Filter::default(),
+            sort: SortMode::default(),
+            tag: None,
+            search: String::new(),
+            settings: Settings::default(),
+            cursor: None,
+            lists: vec![TodoList::default()],
+            current_list: 0,
+            next_id: 1,
+            store: Rc::new(MemoryStore::default()),
+            history: History::default(),
+            toast: None,
+            clock: Rc::new(SystemClock::default()),
+            location: AppRoute::default(),
+            edit_entry: None,
+        }
+    }
+}
+
+impl Todo {
+    /// Create todo lists persisted in the given store, loading the items of the first list
+    /// already stored there.
+    pub fn with_store(store: Rc<dyn TodoStore>) -> Self {
+        let lists = lists::read_lists(&*store);
+        let settings = settings::read_settings(&*store);
+
+        let mut todo = Todo {
+            store: store,
+            lists,
+            settings,
+            .. Todo::default()
+        };
+        todo.switch_list(0);
+        todo
+    }
+
+    pub fn with_items(items: Vec<Item>) -> Self {
+        let next_id = item::next_free_id(&items);
+
+        Todo {
+            history: History::with_saved(Snapshot {
+                items: items.clone(),
+                archive: vec![],
+                trash: vec![],
+            }),
+            items,
+            next_id: next_id,
+            .. Todo::default()
+        }
+    }
+
+    /// Add the items archived from the list, keeping new ids clear of theirs so they can be
+    /// restored.
+    pub fn with_archive(self, archive: Vec<Item>) -> Self {
+        let next_id = self.next_id.max(item::next_free_id(&archive));
+        let mut todo = Todo {
+            archive,
+            next_id,
+            .. self
+        };
+        todo.history = History::with_saved(todo.snapshot());
+        todo
+    }
+
+    /// Add the items removed from the list, keeping new ids clear of theirs so they can be
+    /// restored.
+    pub fn with_trash(self, trash: Vec<Item>) -> Self {
+        let next_id = self.next_id.max(item::next_free_id(&trash));
+        let mut todo = Todo {
+            trash,
+            next_id,
+            .. self
+        };
+        todo.history = History::with_saved(todo.snapshot());
+        todo
+    }
+
+    /// Use the given clock to determine the current date.
+    pub fn with_clock(self, clock: Rc<dyn Clock>) -> Self {
+        Todo {
+            clock,
+            .. self
+        }
+    }
+
+    /// The current local date.
+    pub fn today(&self) -> NaiveDate {
+        self.clock.today()
+    }
+
+    /// The current time.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// What the item being added will be, going by the quick add syntax.
+    pub fn pending_quick_add(&self) -> QuickAdd {
+        QuickAdd::parse(&self.pending_item, self.today())
+    }
+
+    /// The items shown with the current filter, in display order.
+    pub fn visible_items(&self) -> Vec<&Item> {
+        let today = self.today();
+        let mut items: Vec<_> = self.items.iter()
+            .filter(|item| self.filter.matches(item, today))
+            .filter(|item| match self.tag {
+                Some(ref tag) => item.tags.contains(tag),
+                None => true,
+            })
+            .filter(|item| item.any(&|item| self.search_matches(&item.text).is_some()))
+            .collect();
+
+        if self.filter == Filter::Due {
+            items.sort_by_key(|item| item.due);
+        }
+        self.sort.sort(&mut items);
+
+        items
+    }
+
+    /// Where the current search matches some text, see `search::find`.
+    pub fn search_matches(&self, text: &str) -> Option<Vec<Range<usize>>> {
+        search::find(text, &self.search, self.settings.fuzzy_search)
+    }
+
+    /// The ids of all the items and subtasks shown with the current filter, in display order.
+    pub fn visible_ids(&self) -> Vec<ItemId> {
+        fn push(todo: &Todo, item: &Item, ids: &mut Vec<ItemId>) {
+            ids.push(item.id);
+            for child in todo.visible_children(item) {
+                push(todo, child, ids);
+            }
+        }
+
+        let mut ids = vec![];
+        for item in self.visible_items() {
+            push(self, item, &mut ids);
+        }
+        ids
+    }
+
+    /// The subtasks of an item shown with the current filter, in display order.
+    ///
+    /// All subtasks of items matching the search are shown, otherwise only those that match.
+    pub fn visible_children<'a>(&self, item: &'a Item) -> Vec<&'a Item> {
+        let today = self.today();
+        let parent_matches = self.search_matches(&item.text).is_some();
+        let mut children: Vec<_> = item.children.iter()
+            .filter(|child| self.filter.matches(child, today))
+            .filter(|child| parent_matches || child.any(&|item| self.search_matches(&item.text).is_some()))
+            .collect();
+
+        self.sort.sort(&mut children);
+        children
+    }
+
+    /// The list currently shown.
+    pub fn current_list(&self) -> &TodoList {
+        &self.lists[self.current_list]
+    }
+
+    fn list_position(&self, name: &str) -> Option<usize> {
+        self.lists.iter().position(|list| list.name == name)
+    }
+
+    /// Show the list at the given index, loading its items from storage.
+    fn switch_list(&mut self, i: usize) {
+        let key = &self.lists[i].key;
+        let items = read_items_from_storage(&*self.store, key);
+        let archive = read_items_from_storage(&*self.store, &lists::archive_key(key));
+        let trash = read_items_from_storage(&*self.store, &lists::trash_key(key));
+
+        self.current_list = i;
+        // restored items keep their ids
+        self.next_id = item::next_free_id(&items)
+            .max(item::next_free_id(&archive))
+            .max(item::next_free_id(&trash));
+        self.items = items;
+        self.archive = archive;
+        self.trash = trash;
+        self.history = History::with_saved(self.snapshot());
+        self.pending_edit = None;
+        self.toast = None;
+    }
+
+    /// The state undo and redo go back and forth between.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            items: self.items.clone(),
+            archive: self.archive.clone(),
+            trash: self.trash.clone(),
+        }
+    }
+
+    /// Archived items grouped by the day they were completed, most recent first. Items completed
+    /// before completion times were recorded come last.
+    pub fn archive_by_date(&self) -> Vec<(Option<NaiveDate>, Vec<&Item>)> {
+        let mut items: Vec<&Item> = self.archive.iter().collect();
+        items.sort_by_key(|item| Reverse(item.completed_at));
+
+        let mut groups: Vec<(Option<NaiveDate>, Vec<&Item>)> = vec![];
+        for item in items {
+            let date = item.completed_at.map(|t| t.with_timezone(&Local).date_naive());
+            match groups.last_mut() {
+                Some((last, group)) if *last == date => group.push(item),
+                _ => groups.push((date, vec![item])),
+            }
+        }
+        groups
+    }
+
+    /// The route for what is currently shown.
+    pub fn route(&self) -> AppRoute {
+        AppRoute {
+            // the first list is shown by default
+            list: if self.current_list == 0 { None } else { Some(self.current_list().name.clone()) },
+            view: self.view,
+            filter: self.filter,
+            item: self.pending_edit.as_ref().map(|&(id, _)| id),
+            edit: self.pending_edit.is_some(),
+            sort: self.sort,
+            tag: self.tag.clone(),
+            search: self.search.clone(),
+        }
+    }
+
+    /// A title for the document describing what is shown, followed by the name of the app.
+    pub fn title(&self, app: &str) -> String {
+        let remaining = self.items.iter()
+            .filter(|item| !item.is_complete)
+            .count();
+
+        let mut title = String::new();
+        if remaining > 0 {
+            title.push_str(&format!("({}) ", remaining));
+        }
+        match self.filter {
+            Filter::All => {}
+            Filter::Active => title.push_str("Active • "),
+            Filter::Completed => title.push_str("Completed • "),
+            Filter::Due => title.push_str("Due • "),
+        }
+        title.push_str(app);
+        title
+    }
+
+    /// Add a history entry for the current route, unless it is the current entry already.
+    fn push_history(&mut self, cmds: &mut Commands) {
+        let route = self.route();
+        if route != self.location {
+            self.location = route.clone();
+            cmds.push(Command::PushHistory(route));
+        }
+    }
+
+    /// Update the current history entry to the current route without adding a new one.
+    fn replace_history(&mut self, cmds: &mut Commands) {
+        let route = self.route();
+        if route != self.location {
+            self.location = route.clone();
+            cmds.push(Command::ReplaceHistory(route));
+        }
+    }
+
+    /// Leave the history entry of an edit that is done. Going back to the entry it was started
+    /// from doesn't leave a second entry for the same route behind.
+    fn end_edit_history(&mut self, cmds: &mut Commands) {
+        match self.edit_entry.take() {
+            Some(route) => {
+                self.location = route;
+                cmds.push(Command::PopHistory);
+            }
+            None => self.replace_history(cmds),
+        }
+    }
+
+    /// All the tags used by items, in order.
+    pub fn tags(&self) -> BTreeSet<&str> {
+        self.items.iter()
+            .flat_map(|item| item.tags.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Find the position of the top level item with the given id.
+    pub fn position(&self, id: ItemId) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    /// Find an item or subtask by id.
+    pub fn find(&self, id: ItemId) -> Option<&Item> {
+        item::find(&self.items, id)
+    }
+
+    fn find_mut(&mut self, id: ItemId) -> Option<&mut Item> {
+        item::find_mut(&mut self.items, id)
+    }
+
+    /// Allocate a new unique item id.
+    fn new_id(&mut self) -> ItemId {
+        // ids start at 1 so that 0 can mark items from legacy storage
+        let id = self.next_id.max(1);
+        self.next_id = id + 1;
+        id
+    }
+
+    /// Give an item and its subtasks new ids, leaving everything else as it is.
+    fn assign_new_ids(&mut self, item: &mut Item) {
+        item.id = self.new_id();
+        for child in &mut item.children {
+            self.assign_new_ids(child);
+        }
+    }
+
+    /// Add the next copy of a completed recurring item next to it.
+    fn recur(&mut self, id: ItemId) {
+        let today = self.today();
+        let mut next = match self.find_mut(id) {
+            Some(item) => {
+                // the rule moves to the new copy, so completing this one again doesn't add another
+                let recurrence = match item.recurrence.take() {
+                    Some(recurrence) => recurrence,
+                    None => return,
+                };
+                let mut next = item.clone();
+                next.recurrence = Some(recurrence);
+
+                // an item completed late comes back after today, rather than overdue
+                let mut due = recurrence.next(item.due.unwrap_or(today));
+                while due <= today {
+                    due = recurrence.next(due);
+                }
+                next.due = Some(due);
+                next
+            }
+            None => return,
+        };
+
+        self.renew(&mut next);
+        if let Some(siblings) = item::siblings_mut(&mut self.items, id) {
+            siblings.push(next);
+        }
+    }
+
+    /// Turn a copy of an item into a new incomplete item, with new ids for it and its subtasks.
+    fn renew(&mut self, item: &mut Item) {
+        item.id = self.new_id();
+        item.is_complete = false;
+        item.created_at = Some(self.now());
+        item.completed_at = None;
+        for child in &mut item.children {
+            self.renew(child);
+        }
+    }
+}
+
+/// The state undo and redo step through.
+#[derive(Clone,PartialEq,Default)]
+struct Snapshot {
+    items: Vec<Item>,
+    archive: Vec<Item>,
+    trash: Vec<Item>,
+}
+
+/// A short notice shown after an action.
+#[derive(PartialEq,Debug)]
+pub struct Toast {
+    pub message: String,
+    /// Whether to offer undoing the action.
+    pub undoable: bool,
+}
+
+impl Toast {
+    fn undoable(message: impl Into<String>) -> Self {
+        Toast {
+            message: message.into(),
+            undoable: true,
+        }
+    }
+
+    fn notice(message: impl Into<String>) -> Self {
+        Toast {
+            message: message.into(),
+            undoable: false,
+        }
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "item" } else { "items" }
+}
+
+#[derive(PartialEq,Clone,Debug)]
+pub enum Message {
+    UpdatePending(String),
+    AddTodo,
+    /// Add an item for each of the given texts, as when pasting a list.
+    AddTodos(Vec<String>),
+    RemoveTodo(ItemId),
+    ToggleTodo(ItemId),
+    /// Add a subtask with the given text to an item.
+    AddSubtask(ItemId, String),
+    /// Move an item to the position of another item with the same parent, before it if moving
+    /// up and after it if moving down.
+    MoveTodo { from: ItemId, to: ItemId },
+    /// Move the cursor to the next visible item.
+    CursorDown,
+    /// Move the cursor to the previous visible item.
+    CursorUp,
+    ToggleAtCursor,
+    EditAtCursor,
+    RemoveAtCursor,
+    FocusNewTodo,
+    EditTodo(ItemId),
+    UpdateEdit(String),
+    SaveEdit,
+    AbortEdit,
+    /// Move completed items to the archive.
+    ClearCompleted,
+    /// Archive items completed more than the given number of days ago.
+    AutoArchive(u32),
+    /// Move an item from the archive back to the list.
+    RestoreArchived(ItemId),
+    /// Remove an item from the archive for good.
+    DeleteArchived(ItemId),
+    /// Move an item from the trash back to the list. Subtasks come back as top level items.
+    RestoreTrashed(ItemId),
+    /// Remove everything in the trash for good.
+    EmptyTrash,
+    /// Remove items moved to the trash more than the given number of days ago for good.
+    PurgeTrash(u32),
+    ShowView(View),
+    ToggleAll,
+    ShowAll(bool),
+    ShowActive(bool),
+    ShowCompleted(bool),
+    ShowDue(bool),
+    SortBy(SortMode),
+    ShowTag(Option<String>),
+    UpdateSearch(String),
+    Navigate(AppRoute),
+    /// The url didn't match any route, show the default one instead.
+    UnknownRoute(String),
+    /// Create a list with the given name and switch to it.
+    CreateList(String),
+    RenameList(String, String),
+    DeleteList(String),
+    SwitchList(String),
+    /// Set the due date of an item, clearing it also clears the time.
+    SetDue(ItemId, Option<NaiveDate>),
+    SetDueTime(ItemId, Option<NaiveTime>),
+    CyclePriority(ItemId),
+    SetRecurrence(ItemId, Option<Recurrence>),
+    /// Copy a link to the given item to the clipboard.
+    CopyLink(ItemId),
+    Undo,
+    Redo,
+    DismissToast,
+    Export(Format),
+    Import,
+    Imported(Format, String),
+    ItemsChanged,
+    ArchiveChanged,
+    TrashChanged,
+    /// The items (or archived or removed items) stored under the given key were changed elsewhere.
+    ItemsReloaded(String, Vec<Item>),
+    ListsChanged,
+    ListsReloaded(Vec<TodoList>),
+    /// Choose whether completing an item also completes its subtasks.
+    SetCompleteChildren(bool),
+    /// Choose whether searches also match the characters of the query in order.
+    SetFuzzySearch(bool),
+    SettingsChanged,
+    /// The settings were changed elsewhere.
+    SettingsReloaded(Settings),
+}
+
+#[derive(Clone)]
+pub enum Command {
+    FocusPending,
+    FocusEdit,
+    /// Add a history entry for the given route.
+    PushHistory(AppRoute),
+    /// Change the current history entry to the given route.
+    ReplaceHistory(AppRoute),
+    /// Go back to the previous history entry.
+    PopHistory,
+    /// Change the title of the document.
+    SetTitle(String),
+    /// Scroll the given item into view.
+    ScrollTo(ItemId),
+    /// Copy a link to the given url fragment to the clipboard.
+    CopyLink(String),
+    /// Save data under the given key.
+    UpdateStorage(Rc<dyn TodoStore>, String, String),
+    /// Remove the data stored under the given key.
+    ClearStorage(Rc<dyn TodoStore>, String),
+    /// Offer the given data for download as a file in the given format.
+    Download(Format, String),
+    /// Ask the user for a file to import, the contents should be sent back with
+    /// `Message::Imported`.
+    Upload,
+}
+
+/// Commands generated while processing a message.
+///
+/// Commands in `immediate` should be processed right away, those in `post_render` after the
+/// resulting changes have been rendered.
+#[derive(Default)]
+pub struct Commands {
+    pub immediate: Vec<Command>,
+    pub post_render: Vec<Command>,
+}
+
+impl Commands {
+    pub fn push(&mut self, cmd: Command) {
+        self.immediate.push(cmd);
+    }
+}
+
+impl Todo {
+    /// Process a message, updating the model and generating any resulting commands.
+    pub fn update(&mut self, msg: Message, cmds: &mut Commands) {
+        use Message::*;
+
+        match msg {
+            UpdatePending(text) => {
+                self.pending_item = text
+            }
+            AddTodo => {
+                let quick_add = self.pending_quick_add();
+                if quick_add.text.is_empty() {
+                    return;
+                }
+
+                let id = self.new_id();
+                self.items.push(Item {
+                    id: id,
+                    text: quick_add.text,
+                    tags: quick_add.tags,
+                    due: quick_add.due,
+                    due_time: quick_add.due_time,
+                    priority: quick_add.priority,
+                    created_at: Some(self.now()),
+                    .. Item::default()
+                });
+                self.pending_item.clear();
+                self.update(ItemsChanged, cmds);
+            }
+            AddTodos(texts) => {
+                let now = self.now();
+                let mut count = 0;
+                for text in texts {
+                    let (text, tags) = parse_tags(&text);
+                    if text.is_empty() {
+                        continue;
+                    }
+
+                    let id = self.new_id();
+                    self.items.push(Item {
+                        id,
+                        text,
+                        tags,
+                        created_at: Some(now),
+                        .. Item::default()
+                    });
+                    count += 1;
+                }
+
+                if count > 0 {
+                    self.update(ItemsChanged, cmds);
+                    self.toast = Some(Toast::undoable(format!("Added {} {}", count, plural(count))));
+                }
+            }
+            RemoveTodo(id) => {
+                if let Some(mut item) = item::remove(&mut self.items, id) {
+                    item.deleted_at = Some(self.now());
+                    self.trash.push(item);
+
+                    self.update(TrashChanged, cmds);
+                    self.update(ItemsChanged, cmds);
+                    self.toast = Some(Toast::undoable("Moved 1 item to the trash"));
+                }
+            }
+            ToggleTodo(id) => {
+                let complete_children = self.settings.complete_children;
+                let now = self.now();
+                let mut recurring = vec![];
+                if let Some(item) = self.find_mut(id) {
+                    let completing = !item.is_complete;
+                    item::mark_complete(item, completing, completing && complete_children, now, &mut recurring);
+                    for id in recurring {
+                        self.recur(id);
+                    }
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            AddSubtask(parent, text) => {
+                let (text, tags) = parse_tags(&text);
+                if text.is_empty() || self.find(parent).is_none() {
+                    return;
+                }
+
+                let id = self.new_id();
+                let now = self.now();
+                if let Some(parent) = self.find_mut(parent) {
+                    parent.children.push(Item {
+                        id,
+                        text,
+                        tags,
+                        created_at: Some(now),
+                        .. Item::default()
+                    });
+                }
+                self.update(ItemsChanged, cmds);
+            }
+            MoveTodo { from, to } => {
+                if from == to {
+                    return;
+                }
+
+                let siblings = match item::siblings_mut(&mut self.items, from) {
+                    Some(siblings) => siblings,
+                    None => return,
+                };
+
+                // positions are in the unfiltered list, so hidden items stay where they are
+                let i = siblings.iter().position(|item| item.id == from);
+                let j = siblings.iter().position(|item| item.id == to);
+                if let (Some(i), Some(j)) = (i, j) {
+                    let item = siblings.remove(i);
+                    siblings.insert(j, item);
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            CursorDown | CursorUp => {
+                let ids = self.visible_ids();
+                let i = self.cursor.and_then(|id| ids.iter().position(|&i| i == id));
+
+                // start from the top (or bottom) if nothing visible is selected yet
+                let i = match (msg == CursorDown, i) {
+                    (true, Some(i)) => (i + 1).min(ids.len() - 1),
+                    (false, Some(i)) => i.saturating_sub(1),
+                    (true, None) => 0,
+                    (false, None) => ids.len().saturating_sub(1),
+                };
+
+                self.cursor = ids.get(i).copied();
+            }
+            ToggleAtCursor => {
+                if let Some(id) = self.cursor {
+                    self.update(ToggleTodo(id), cmds);
+                }
+            }
+            EditAtCursor => {
+                if let Some(id) = self.cursor {
+                    self.update(EditTodo(id), cmds);
+                }
+            }
+            RemoveAtCursor => {
+                if let Some(id) = self.cursor {
+                    // keep the cursor in the same place in the list
+                    let ids = self.visible_ids();
+                    let next = ids.iter()
+                        .position(|&i| i == id)
+                        .and_then(|i| ids.get(i + 1).or_else(|| i.checked_sub(1).and_then(|i| ids.get(i))))
+                        .copied();
+
+                    self.update(RemoveTodo(id), cmds);
+                    self.cursor = next.filter(|&next| self.find(next).is_some());
+                }
+            }
+            FocusNewTodo => {
+                cmds.push(Command::FocusPending);
+            }
+            EditTodo(id) => {
+                if let Some(item) = self.find(id) {
+                    self.pending_edit = Some((id, item.text_with_tags()));
+                    cmds.post_render.push(Command::FocusEdit);
+
+                    // the edit gets an entry of its own, so going back ends it and going forward
+                    // opens it again
+                    if self.edit_entry.is_some() {
+                        self.replace_history(cmds);
+                    }
+                    else {
+                        let route = self.location.clone();
+                        self.push_history(cmds);
+                        if self.location != route {
+                            self.edit_entry = Some(route);
+                        }
+                    }
+                }
+            }
+            UpdateEdit(text) => {
+                match self.pending_edit {
+                    Some((_, ref mut pending_text)) => {
+                        *pending_text = text;
+                    }
+                    _ => panic!("UpdateEdit called with no pending edit"),
+                }
+            }
+            SaveEdit => {
+                match self.pending_edit.take() {
+                    Some((id, text)) => {
+                        let (text, tags) = parse_tags(&text);
+                        if text.is_empty() {
+                            self.update(RemoveTodo(id), cmds);
+                        }
+                        else if let Some(item) = self.find_mut(id) {
+                            item.text = text;
+                            item.tags = tags;
+                        }
+                    }
+                    _ => panic!("SaveEdit called with no pending edit"),
+                }
+                self.update(ItemsChanged, cmds);
+                self.end_edit_history(cmds);
+            }
+            AbortEdit => {
+                self.pending_edit = None;
+                self.end_edit_history(cmds);
+            }
+            ClearCompleted => {
+                let archived = item::remove_matching(&mut self.items, &|item| item.is_complete);
+                let count = archived.len();
+                self.archive.extend(archived);
+
+                self.update(ArchiveChanged, cmds);
+                self.update(ItemsChanged, cmds);
+                if count > 0 {
+                    self.toast = Some(Toast::undoable(format!("Archived {} completed {}", count, plural(count))));
+                }
+            }
+            AutoArchive(days) => {
+                let cutoff = self.now() - chrono::Duration::days(days.into());
+                let archived = item::remove_matching(&mut self.items, &|item| {
+                    item.is_complete && matches!(item.completed_at, Some(t) if t < cutoff)
+                });
+                if archived.is_empty() {
+                    return;
+                }
+
+                let count = archived.len();
+                self.archive.extend(archived);
+
+                self.update(ArchiveChanged, cmds);
+                self.update(ItemsChanged, cmds);
+                self.toast = Some(Toast::undoable(format!(
+                    "Archived {} {} completed over {} days ago", count, plural(count), days,
+                )));
+            }
+            RestoreArchived(id) => {
+                if let Some(i) = self.archive.iter().position(|item| item.id == id) {
+                    let item = self.archive.remove(i);
+                    self.items.push(item);
+
+                    self.update(ArchiveChanged, cmds);
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            DeleteArchived(id) => {
+                if let Some(i) = self.archive.iter().position(|item| item.id == id) {
+                    self.archive.remove(i);
+                    self.update(ArchiveChanged, cmds);
+                    self.toast = Some(Toast::undoable("Deleted 1 item"));
+                }
+            }
+            RestoreTrashed(id) => {
+                if let Some(i) = self.trash.iter().position(|item| item.id == id) {
+                    let mut item = self.trash.remove(i);
+                    item.deleted_at = None;
+                    self.items.push(item);
+
+                    self.update(TrashChanged, cmds);
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            EmptyTrash => {
+                let count = self.trash.len();
+                if count == 0 {
+                    return;
+                }
+
+                self.trash.clear();
+                self.update(TrashChanged, cmds);
+                self.toast = Some(Toast::undoable(format!("Deleted {} {}", count, plural(count))));
+            }
+            PurgeTrash(days) => {
+                let cutoff = self.now() - chrono::Duration::days(days.into());
+                let count = self.trash.len();
+                self.trash.retain(|item| !matches!(item.deleted_at, Some(t) if t < cutoff));
+
+                if self.trash.len() != count {
+                    self.update(TrashChanged, cmds);
+                }
+            }
+            ShowView(view) => {
+                self.view = view;
+                self.push_history(cmds);
+            }
+            ToggleAll => {
+                let all_complete = self.items.iter().all(|item| item.is_complete);
+
+                let now = self.now();
+                let mut recurring = vec![];
+
+                for item in self.items.iter_mut() {
+                    item::mark_complete(item, !all_complete, true, now, &mut recurring);
+                }
+                for id in recurring {
+                    self.recur(id);
+                }
+
+                self.update(ItemsChanged, cmds);
+            }
+            ShowAll(push_history) => {
+                self.filter = Filter::All;
+                if push_history {
+                    self.push_history(cmds);
+                }
+            }
+            ShowActive(push_history) => {
+                self.filter = Filter::Active;
+                if push_history {
+                    self.push_history(cmds);
+                }
+            }
+            ShowCompleted(push_history) => {
+                self.filter = Filter::Completed;
+                if push_history {
+                    self.push_history(cmds);
+                }
+            }
+            ShowDue(push_history) => {
+                self.filter = Filter::Due;
+                if push_history {
+                    self.push_history(cmds);
+                }
+            }
+            SortBy(sort) => {
+                self.sort = sort;
+                self.push_history(cmds);
+            }
+            ShowTag(tag) => {
+                self.tag = tag;
+                self.push_history(cmds);
+            }
+            UpdateSearch(search) => {
+                self.search = search;
+                // don't add an entry for every key typed
+                self.replace_history(cmds);
+            }
+            Navigate(route) => {
+                // the browser is already showing this route
+                self.location = route.clone();
+                self.pending_edit = None;
+                self.edit_entry = None;
+
+                self.view = route.view;
+                self.filter = route.filter;
+                self.sort = route.sort;
+                self.tag = route.tag;
+                self.search = route.search;
+
+                // unknown lists are ignored
+                let list = match route.list {
+                    Some(name) => self.list_position(&name),
+                    None => Some(0),
+                };
+                match list {
+                    Some(i) if i != self.current_list => self.switch_list(i),
+                    _ => {}
+                }
+
+                // links to unknown items just show the list
+                if let Some(id) = route.item.filter(|&id| self.find(id).is_some()) {
+                    self.cursor = Some(id);
+                    cmds.post_render.push(Command::ScrollTo(id));
+                    if route.edit {
+                        self.update(EditTodo(id), cmds);
+                    }
+                }
+            }
+            CopyLink(id) => {
+                let route = AppRoute {
+                    item: Some(id),
+                    edit: false,
+                    .. self.route()
+                };
+                cmds.push(Command::CopyLink(route.to_url()));
+                self.toast = Some(Toast::notice("Copied link"));
+            }
+            UnknownRoute(url) => {
+                self.update(Navigate(AppRoute::default()), cmds);
+                cmds.push(Command::ReplaceHistory(self.route()));
+                self.toast = Some(Toast::notice(format!("Unknown address {}", url)));
+            }
+            CreateList(name) => {
+                if let Some(name) = lists::normalize_name(&name) {
+                    if self.list_position(&name).is_none() {
+                        let key = lists::new_key(&self.lists);
+                        self.lists.push(TodoList { name: name.clone(), key });
+                        self.update(ListsChanged, cmds);
+                    }
+                    self.update(SwitchList(name), cmds);
+                }
+            }
+            RenameList(from, to) => {
+                let to = match lists::normalize_name(&to) {
+                    Some(to) => to,
+                    None => return,
+                };
+
+                if let Some(i) = self.list_position(&from) {
+                    if self.list_position(&to).is_some() {
+                        self.toast = Some(Toast::notice(format!("There is already a list named {}", to)));
+                        return;
+                    }
+
+                    self.lists[i].name = to;
+                    self.update(ListsChanged, cmds);
+                    if i == self.current_list {
+                        self.push_history(cmds);
+                    }
+                }
+            }
+            DeleteList(name) => {
+                // there is always at least one list
+                if self.lists.len() == 1 {
+                    return;
+                }
+
+                if let Some(i) = self.list_position(&name) {
+                    let list = self.lists.remove(i);
+                    cmds.push(Command::ClearStorage(self.store.clone(), lists::archive_key(&list.key)));
+                    cmds.push(Command::ClearStorage(self.store.clone(), lists::trash_key(&list.key)));
+                    cmds.push(Command::ClearStorage(self.store.clone(), list.key));
+                    self.update(ListsChanged, cmds);
+
+                    if i == self.current_list {
+                        self.switch_list(0);
+                        self.push_history(cmds);
+                    }
+                    else if i < self.current_list {
+                        self.current_list -= 1;
+                    }
+
+                    self.toast = Some(Toast::notice(format!("Deleted list {}", name)));
+                }
+            }
+            SwitchList(name) => {
+                if let Some(i) = self.list_position(&name) {
+                    if i != self.current_list {
+                        self.switch_list(i);
+                        self.push_history(cmds);
+                    }
+                }
+            }
+            SetRecurrence(id, recurrence) => {
+                if let Some(item) = self.find_mut(id) {
+                    item.recurrence = recurrence;
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            CyclePriority(id) => {
+                if let Some(item) = self.find_mut(id) {
+                    item.priority = item.priority.next();
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            SetDue(id, due) => {
+                if let Some(item) = self.find_mut(id) {
+                    item.due = due;
+                    if due.is_none() {
+                        item.due_time = None;
+                    }
+                    self.update(ItemsChanged, cmds);
+                }
+            }
+            SetDueTime(id, time) => {
+                if let Some(item) = self.find_mut(id) {
+                    if item.due.is_some() {
+                        item.due_time = time;
+                        self.update(ItemsChanged, cmds);
+                    }
+                }
+            }
+            Undo => {
+                if let Some(snapshot) = self.history.undo() {
+                    self.restore(snapshot, cmds);
+                }
+            }
+            Redo => {
+                if let Some(snapshot) = self.history.redo() {
+                    self.restore(snapshot, cmds);
+                }
+            }
+            DismissToast => {
+                self.toast = None;
+            }
+            Export(format) => {
+                cmds.push(Command::Download(format, export::export(&self.items, format)));
+            }
+            Import => {
+                cmds.push(Command::Upload);
+            }
+            Imported(format, data) => {
+                match export::import(&data, format) {
+                    Ok(items) => {
+                        let count = items.len();
+                        for mut item in items {
+                            self.assign_new_ids(&mut item);
+                            self.items.push(item);
+                        }
+
+                        self.update(ItemsChanged, cmds);
+                        self.toast = Some(Toast::undoable(format!("Imported {} {}", count, plural(count))));
+                    }
+                    Err(e) => {
+                        error!("error importing {}: {}", format.name(), e);
+                        self.toast = Some(Toast::notice(format!("Couldn't import {}: {}", format.name(), e)));
+                    }
+                }
+            }
+            ItemsReloaded(key, items) => {
+                // the items were changed elsewhere (e.g. another tab) and are already persisted
+                let list_key = &self.current_list().key;
+                if key == *list_key {
+                    self.items = items;
+                }
+                else if key == lists::archive_key(list_key) {
+                    self.archive = items;
+                }
+                else if key == lists::trash_key(list_key) {
+                    self.trash = items;
+                }
+                else {
+                    return;
+                }
+
+                self.next_id = self.next_id
+                    .max(item::next_free_id(&self.items))
+                    .max(item::next_free_id(&self.archive))
+                    .max(item::next_free_id(&self.trash));
+                // what the toast offers to undo is no longer in the history
+                self.history = History::with_saved(self.snapshot());
+                self.toast = None;
+
+                if let Some((id, _)) = self.pending_edit {
+                    if self.find(id).is_none() {
+                        self.pending_edit = None;
+                        self.end_edit_history(cmds);
+                    }
+                }
+            }
+            ItemsChanged => {
+                if self.history.record(&self.snapshot()) {
+                    self.toast = None;
+                }
+
+                cmds.push(Command::UpdateStorage(
+                    self.store.clone(),
+                    self.current_list().key.clone(),
+                    schema::encode(&self.items),
+                ));
+            }
+            ArchiveChanged => {
+                if self.history.record(&self.snapshot()) {
+                    self.toast = None;
+                }
+
+                cmds.push(Command::UpdateStorage(
+                    self.store.clone(),
+                    lists::archive_key(&self.current_list().key),
+                    schema::encode(&self.archive),
+                ));
+            }
+            TrashChanged => {
+                if self.history.record(&self.snapshot()) {
+                    self.toast = None;
+                }
+
+                cmds.push(Command::UpdateStorage(
+                    self.store.clone(),
+                    lists::trash_key(&self.current_list().key),
+                    schema::encode(&self.trash),
+                ));
+            }
+            ListsChanged => {
+                cmds.push(Command::UpdateStorage(
+                    self.store.clone(),
+                    LISTS_KEY.to_owned(),
+                    lists::encode(&self.lists),
+                ));
+            }
+            ListsReloaded(lists) => {
+                if lists.is_empty() {
+                    return;
+                }
+
+                // keep showing the current list if it's still around
+                let key = self.current_list().key.clone();
+                self.lists = lists;
+                match self.lists.iter().position(|list| list.key == key) {
+                    Some(i) => self.current_list = i,
+                    None => self.switch_list(0),
+                }
+            }
+            SetCompleteChildren(complete_children) => {
+                self.settings.complete_children = complete_children;
+                self.update(SettingsChanged, cmds);
+            }
+            SetFuzzySearch(fuzzy_search) => {
+                self.settings.fuzzy_search = fuzzy_search;
+                self.update(SettingsChanged, cmds);
+            }
+            SettingsChanged => {
+                cmds.push(Command::UpdateStorage(
+                    self.store.clone(),
+                    SETTINGS_KEY.to_owned(),
+                    settings::encode(&self.settings),
+                ));
+            }
+            SettingsReloaded(settings) => {
+                self.settings = settings;
+            }
+        }
+    }
+}
+
+impl Todo {
+    /// Replace the items with a snapshot from history and persist them.
+    fn restore(&mut self, snapshot: Snapshot, cmds: &mut Commands) {
+        let archive_changed = snapshot.archive != self.archive;
+        let trash_changed = snapshot.trash != self.trash;
+        self.items = snapshot.items;
+        self.archive = snapshot.archive;
+        self.trash = snapshot.trash;
+        self.toast = None;
+
+        // the edited item may be gone now
+        if let Some((id, _)) = self.pending_edit {
+            if self.find(id).is_none() {
+                self.pending_edit = None;
+            }
+        }
+
+        // history was already updated, this will just persist the change
+        self.update(Message::ItemsChanged, cmds);
+        if archive_changed {
+            self.update(Message::ArchiveChanged, cmds);
+        }
+        if trash_changed {
+            self.update(Message::TrashChanged, cmds);
+        }
+    }
+}
+
+fn read_items_from_storage(store: &dyn TodoStore, key: &str) -> Vec<Item> {
+    store.load(key)
+        .map_or(vec![], |data|
+            match schema::decode(&data) {
+                Ok(items) => items,
+                Err(e) => {
+                    let backup_key = lists::backup_key(key);
+                    error!("error reading items from storage: {}", e);
+                    error!("saving unreadable data under '{}'", backup_key);
+                    store.save(&backup_key, &data);
+                    vec![]
+                }
+            }
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::item::assign_missing_ids;
+
+    #[test]
+    fn add_todo() {
+        let mut todomvc = Todo::default();
+
+        todomvc.update(Message::UpdatePending("item".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].text, "item");
+        assert_eq!(todomvc.items[0].is_complete, false);
+    }
+
+    #[test]
+    fn add_todos() {
+        let mut todomvc = todo_with_items(&["a"]);
+
+        let pasted = "Groceries:\r\n- milk #shop\n* [ ] eggs\n\n  - [x] bread  \n[X]flour\n-5 degrees\n#later\n";
+        let mut cmds = Commands::default();
+        todomvc.update(Message::AddTodos(parse_lines(pasted)), &mut cmds);
+
+        assert_eq!(texts(&todomvc.items), ["a", "Groceries:", "milk", "eggs", "bread", "flour", "-5 degrees"]);
+        assert_eq!(todomvc.items[2].tags.iter().collect::<Vec<_>>(), ["shop"]);
+        assert_eq!(todomvc.items.iter().map(|item| item.id).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(todomvc.toast.as_ref().unwrap().message, "Added 6 items");
+
+        // the items are saved together and undone together
+        assert_eq!(cmds.immediate.iter().filter(|cmd| matches!(cmd, Command::UpdateStorage(..))).count(), 1);
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["a"]);
+
+        // nothing to add
+        let mut cmds = Commands::default();
+        todomvc.update(Message::AddTodos(parse_lines("\n - \n[ ]\n")), &mut cmds);
+        assert!(cmds.immediate.is_empty());
+        assert_eq!(texts(&todomvc.items), ["a"]);
+    }
+
+    #[test]
+    fn remove_todo() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item::default());
+
+        todomvc.update(Message::RemoveTodo(0), &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 0);
+    }
+
+    #[test]
+    fn toggle_todo() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item::default());
+
+        todomvc.update(Message::ToggleTodo(0), &mut Commands::default());
+
+        assert_eq!(todomvc.items[0].is_complete, true);
+    }
+
+    #[test]
+    fn timestamps() {
+        let clock = Rc::new(clock::FixedClock { today: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap() });
+        let now = clock.now();
+        let mut todomvc = Todo::default().with_clock(clock);
+
+        todomvc.update(Message::UpdatePending("a".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+        todomvc.update(Message::AddSubtask(1, "b".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.items[0].created_at, Some(now));
+        assert_eq!(todomvc.items[0].children[0].created_at, Some(now));
+        assert_eq!(todomvc.items[0].completed_at, None);
+
+        todomvc.update(Message::ToggleTodo(1), &mut Commands::default());
+        assert_eq!(todomvc.items[0].completed_at, Some(now));
+        assert_eq!(todomvc.items[0].children[0].completed_at, Some(now));
+        assert_eq!(todomvc.items[0].activity(now + chrono::Duration::hours(2)), "Added 2h ago · Completed 2h ago");
+
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        assert_eq!(todomvc.items[0].completed_at, None);
+        assert_eq!(todomvc.items[0].children[0].completed_at, None);
+
+        // old data without timestamps still loads
+        let item: Item = serde_json::from_str(r#"{"id": 1, "title": "a", "completed": true}"#).unwrap();
+        assert_eq!((item.created_at, item.completed_at), (None, None));
+        let item: Item = serde_json::from_str(&serde_json::to_string(&todomvc.items[0]).unwrap()).unwrap();
+        assert_eq!(item.created_at, Some(now));
+    }
+
+    #[test]
+    fn toggle_recurring_todo() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut todomvc = Todo::with_items(vec![
+            Item {
+                id: 1,
+                text: "weekly report".to_owned(),
+                due: NaiveDate::from_ymd_opt(2024, 2, 28),
+                recurrence: Some(Recurrence::Weekly(chrono::Weekday::Fri)),
+                .. Item::default()
+            },
+            Item {
+                id: 2,
+                text: "standup prep".to_owned(),
+                recurrence: Some(Recurrence::Daily),
+                .. Item::default()
+            },
+        ]).with_clock(Rc::new(clock::FixedClock { today }));
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ToggleTodo(1), &mut cmds);
+        persist(cmds);
+
+        assert_eq!(todomvc.items.len(), 3);
+        assert!(todomvc.items[0].is_complete);
+        assert_eq!(todomvc.items[0].recurrence, None);
+        let next = &todomvc.items[2];
+        assert_eq!(next.text, "weekly report");
+        assert!(!next.is_complete);
+        // the friday after the one it was completed on
+        assert_eq!(next.due, NaiveDate::from_ymd_opt(2024, 3, 8));
+        assert_eq!(next.recurrence, Some(Recurrence::Weekly(chrono::Weekday::Fri)));
+        assert!(next.id != 1 && next.id != 2);
+
+        // the copy is persisted with the rest of the list
+        let saved = read_items_from_storage(&*todomvc.store, STORAGE_KEY);
+        assert_eq!(saved, todomvc.items);
+
+        // uncompleting and completing again doesn't add another copy
+        todomvc.update(Message::ToggleTodo(1), &mut Commands::default());
+        todomvc.update(Message::ToggleTodo(1), &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 3);
+
+        // items without a due date recur from today
+        todomvc.update(Message::ToggleTodo(2), &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 4);
+        assert_eq!(todomvc.items[3].due, NaiveDate::from_ymd_opt(2024, 3, 2));
+    }
+
+    #[test]
+    fn toggle_recurring_todo_late() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 13).unwrap();
+        let mut todomvc = Todo::with_items(vec![
+            Item {
+                id: 1,
+                due: NaiveDate::from_ymd_opt(2024, 3, 1),
+                recurrence: Some(Recurrence::Daily),
+                .. Item::default()
+            },
+            Item {
+                id: 2,
+                due: NaiveDate::from_ymd_opt(2024, 1, 31),
+                recurrence: Some(Recurrence::Monthly(31)),
+                .. Item::default()
+            },
+        ]).with_clock(Rc::new(clock::FixedClock { today }));
+
+        todomvc.update(Message::ToggleTodo(1), &mut Commands::default());
+        todomvc.update(Message::ToggleTodo(2), &mut Commands::default());
+
+        // the copies skip the days missed and aren't overdue
+        assert_eq!(todomvc.items[2].due, NaiveDate::from_ymd_opt(2024, 3, 14));
+        assert_eq!(todomvc.items[3].due, NaiveDate::from_ymd_opt(2024, 3, 31));
+        assert!(!todomvc.items[2].is_overdue(today));
+
+        // and stay on their day of the month after a shorter one
+        todomvc.update(Message::ToggleTodo(todomvc.items[3].id), &mut Commands::default());
+        assert_eq!(todomvc.items[4].due, NaiveDate::from_ymd_opt(2024, 4, 30));
+        todomvc.update(Message::ToggleTodo(todomvc.items[4].id), &mut Commands::default());
+        assert_eq!(todomvc.items[5].due, NaiveDate::from_ymd_opt(2024, 5, 31));
+    }
+
+    #[test]
+    fn toggle_all_recurring() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        todomvc.update(Message::SetRecurrence(2, Some(Recurrence::Daily)), &mut Commands::default());
+
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+
+        // the next copy is added, and left incomplete
+        assert_eq!(texts(&todomvc.items), ["a", "b", "b"]);
+        assert!(todomvc.items[0].is_complete && todomvc.items[1].is_complete);
+        assert!(!todomvc.items[2].is_complete);
+        assert_eq!(todomvc.items[1].recurrence, None);
+        assert_eq!(todomvc.items[2].recurrence, Some(Recurrence::Daily));
+
+        // so the series goes on after clearing the completed items
+        todomvc.update(Message::ClearCompleted, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["b"]);
+        assert_eq!(todomvc.items[0].due, Some(todomvc.today() + chrono::Duration::days(1)));
+    }
+
+    #[test]
+    fn toggle_recurring_subtask() {
+        let mut todomvc = todo_with_subtasks();
+        let parent = todomvc.items[0].id;
+        let child = todomvc.items[0].children[1].id;
+        todomvc.update(Message::SetRecurrence(child, Some(Recurrence::EveryDays(3))), &mut Commands::default());
+
+        todomvc.update(Message::ToggleTodo(child), &mut Commands::default());
+
+        // the copy is added next to the completed subtask
+        let children = &todomvc.find(parent).unwrap().children;
+        assert_eq!(texts(children), ["a", "b", "c", "b"]);
+        assert!(children[1].is_complete);
+        assert!(!children[3].is_complete);
+        assert_eq!(children[3].due, Some(todomvc.today() + chrono::Duration::days(3)));
+    }
+
+    #[test]
+    fn toggle_recurring_todo_with_subtasks() {
+        let mut todomvc = todo_with_subtasks();
+        let parent = todomvc.items[0].id;
+        todomvc.update(Message::SetRecurrence(parent, Some(Recurrence::Monthly(1))), &mut Commands::default());
+
+        todomvc.update(Message::ToggleTodo(parent), &mut Commands::default());
+
+        // subtasks are copied, incomplete and with new ids
+        let ids = todomvc.visible_ids();
+        let mut unique = ids.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(ids.len(), unique.len());
+
+        let copy = todomvc.items.last().unwrap();
+        assert_eq!(texts(&copy.children), ["a", "b", "c"]);
+        assert!(!copy.any(&|item| item.is_complete));
+        assert!(todomvc.items[0].children.iter().all(|child| child.is_complete));
+    }
+
+    #[test]
+    fn messages_target_items_by_id() {
+        let mut todomvc = Todo::default();
+
+        for text in &["item1", "item2", "item3"] {
+            todomvc.update(Message::UpdatePending(text.to_string()), &mut Commands::default());
+            todomvc.update(Message::AddTodo, &mut Commands::default());
+        }
+
+        let id2 = todomvc.items[1].id;
+        let id3 = todomvc.items[2].id;
+
+        // removing an item shifts positions, but not ids
+        todomvc.update(Message::RemoveTodo(todomvc.items[0].id), &mut Commands::default());
+        todomvc.update(Message::ToggleTodo(id3), &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 2);
+        assert_eq!(todomvc.items[0].id, id2);
+        assert_eq!(todomvc.items[0].is_complete, false);
+        assert_eq!(todomvc.items[1].id, id3);
+        assert_eq!(todomvc.items[1].is_complete, true);
+
+        // messages for items that no longer exist are ignored
+        todomvc.update(Message::RemoveTodo(id2), &mut Commands::default());
+        todomvc.update(Message::RemoveTodo(id2), &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 1);
+    }
+
+    #[test]
+    fn legacy_items_get_ids() {
+        let mut items: Vec<Item> = serde_json::from_str(r#"[
+            {"title":"one","completed":false},
+            {"title":"two","completed":true},
+            {"id":2,"title":"three","completed":false},
+            {"id":2,"title":"four","completed":false}
+        ]"#).unwrap();
+
+        assign_missing_ids(&mut items);
+
+        let mut ids: Vec<_> = items.iter().map(|item| item.id).collect();
+        assert_eq!(items[2].id, 2);
+        assert!(ids.iter().all(|&id| id != 0));
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 4);
+
+        let mut todomvc = Todo::with_items(items);
+        let id = todomvc.new_id();
+        assert!(todomvc.items.iter().all(|item| item.id != id));
+    }
+
+    #[test]
+    fn save_edit_removes_empty() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            text: "text".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::EditTodo(0), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit("".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 0);
+    }
+
+    #[test]
+    fn save_edit_trims_whitespace() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            text: "text".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::EditTodo(0), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit(" edited text  ".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].text, "edited text");
+    }
+
+    /// A todo with one parent item and subtasks "a", "b" and "c".
+    fn todo_with_subtasks() -> Todo {
+        let mut todomvc = Todo::default();
+        todomvc.update(Message::UpdatePending("parent".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+
+        let parent = todomvc.items[0].id;
+        for text in &["a", "b", "c"] {
+            todomvc.update(Message::AddSubtask(parent, text.to_string()), &mut Commands::default());
+        }
+        todomvc
+    }
+
+    #[test]
+    fn subtasks() {
+        let mut todomvc = todo_with_subtasks();
+        let parent = todomvc.items[0].id;
+        let (a, b, c) = (todomvc.items[0].children[0].id, todomvc.items[0].children[1].id, todomvc.items[0].children[2].id);
+
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].progress(), Some((0, 3)));
+        assert_ne!(a, parent);
+        assert_ne!(a, b);
+
+        // messages reach subtasks
+        todomvc.update(Message::ToggleTodo(b), &mut Commands::default());
+        assert_eq!(todomvc.items[0].progress(), Some((1, 3)));
+        todomvc.update(Message::EditTodo(c), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit("see #work".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(todomvc.find(c).unwrap().text, "see");
+        todomvc.update(Message::RemoveTodo(a), &mut Commands::default());
+        assert_eq!(todomvc.items[0].progress(), Some((1, 2)));
+
+        // subtasks of unknown items are ignored
+        todomvc.update(Message::AddSubtask(999, "x".to_owned()), &mut Commands::default());
+        assert!(todomvc.find(999).is_none());
+
+        // subtasks are persisted along with their parent
+        let items = schema::decode(&schema::encode(&todomvc.items)).unwrap();
+        assert_eq!(items, todomvc.items);
+        assert!(Todo::with_items(items).new_id() > c);
+
+        todomvc.update(Message::ClearCompleted, &mut Commands::default());
+        assert_eq!(todomvc.items[0].progress(), Some((0, 1)));
+    }
+
+    #[test]
+    fn toggle_parent() {
+        let mut todomvc = todo_with_subtasks();
+        let parent = todomvc.items[0].id;
+
+        todomvc.update(Message::ToggleTodo(parent), &mut Commands::default());
+        assert!(todomvc.items[0].children.iter().all(|child| child.is_complete));
+
+        // marking the parent incomplete again leaves the subtasks alone
+        todomvc.update(Message::ToggleTodo(parent), &mut Commands::default());
+        assert!(!todomvc.items[0].is_complete);
+        assert_eq!(todomvc.items[0].progress(), Some((3, 3)));
+
+        let mut todomvc = todo_with_subtasks();
+        todomvc.update(Message::SetCompleteChildren(false), &mut Commands::default());
+        todomvc.update(Message::ToggleTodo(parent), &mut Commands::default());
+        assert!(todomvc.items[0].is_complete);
+        assert_eq!(todomvc.items[0].progress(), Some((0, 3)));
+    }
+
+    #[test]
+    fn settings() {
+        let store: Rc<dyn TodoStore> = Rc::new(MemoryStore::default());
+        let mut todomvc = Todo::with_store(store.clone());
+        assert!(todomvc.settings.complete_children);
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::SetCompleteChildren(false), &mut cmds);
+        assert!(!todomvc.settings.complete_children);
+        persist(cmds);
+
+        // settings are shared by all lists and kept when reloading
+        let reloaded = Todo::with_store(store.clone());
+        assert_eq!(reloaded.settings, todomvc.settings);
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::SetCompleteChildren(true), &mut cmds);
+        todomvc.update(Message::SetFuzzySearch(true), &mut cmds);
+        persist(cmds);
+        let reloaded = Todo::with_store(store);
+        assert!(reloaded.settings.complete_children);
+        assert!(reloaded.settings.fuzzy_search);
+    }
+
+    #[test]
+    fn filter_subtasks() {
+        let mut todomvc = todo_with_subtasks();
+        let b = todomvc.items[0].children[1].id;
+        todomvc.update(Message::ToggleTodo(b), &mut Commands::default());
+
+        let visible = |todomvc: &Todo| -> Vec<(String, Vec<String>)> {
+            todomvc.visible_items().into_iter()
+                .map(|item| (
+                    item.text.clone(),
+                    todomvc.visible_children(item).into_iter().map(|child| child.text.clone()).collect(),
+                ))
+                .collect()
+        };
+
+        // a partially finished parent shows up in both filters with the matching subtasks
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+        assert_eq!(visible(&todomvc), vec![("parent".to_owned(), vec!["a".to_owned(), "c".to_owned()])]);
+        todomvc.update(Message::ShowCompleted(false), &mut Commands::default());
+        assert_eq!(visible(&todomvc), vec![("parent".to_owned(), vec!["b".to_owned()])]);
+
+        // a finished parent is only active while it has unfinished subtasks
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+        assert!(visible(&todomvc).is_empty());
+    }
+
+    fn texts(items: &[Item]) -> Vec<&str> {
+        items.iter().map(|item| item.text.as_str()).collect()
+    }
+
+    fn todo_with_items(texts: &[&str]) -> Todo {
+        let mut todomvc = Todo::default();
+        for text in texts {
+            todomvc.update(Message::UpdatePending(text.to_string()), &mut Commands::default());
+            todomvc.update(Message::AddTodo, &mut Commands::default());
+        }
+        todomvc
+    }
+
+    #[test]
+    fn move_todo() {
+        let mut todomvc = todo_with_items(&["a", "b", "c", "d"]);
+        let id = |todomvc: &Todo, text: &str| todomvc.items.iter().find(|item| item.text == text).unwrap().id;
+
+        // moving down places the item after the target
+        let mut cmds = Commands::default();
+        todomvc.update(Message::MoveTodo { from: id(&todomvc, "a"), to: id(&todomvc, "c") }, &mut cmds);
+        assert_eq!(texts(&todomvc.items), vec!["b", "c", "a", "d"]);
+        assert!(cmds.immediate.iter().any(|cmd| matches!(cmd, Command::UpdateStorage(..))));
+
+        // moving up places it before the target
+        todomvc.update(Message::MoveTodo { from: id(&todomvc, "d"), to: id(&todomvc, "b") }, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), vec!["d", "b", "c", "a"]);
+
+        // moves can be undone
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn move_todo_ignores_invalid_moves() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        let (a, b) = (todomvc.items[0].id, todomvc.items[1].id);
+        todomvc.update(Message::AddSubtask(a, "a.1".to_owned()), &mut Commands::default());
+        let subtask = todomvc.items[0].children[0].id;
+
+        for &(from, to) in &[(a, a), (a, 999), (999, a), (subtask, b), (b, subtask)] {
+            let mut cmds = Commands::default();
+            todomvc.update(Message::MoveTodo { from, to }, &mut cmds);
+            assert!(cmds.immediate.is_empty(), "moving {} to {}", from, to);
+            assert_eq!(texts(&todomvc.items), vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn move_subtasks() {
+        let mut todomvc = todo_with_subtasks();
+        let children = |todomvc: &Todo| texts(&todomvc.items[0].children).join("");
+        let (a, c) = (todomvc.items[0].children[0].id, todomvc.items[0].children[2].id);
+
+        todomvc.update(Message::MoveTodo { from: c, to: a }, &mut Commands::default());
+        assert_eq!(children(&todomvc), "cab");
+    }
+
+    #[test]
+    fn move_todo_with_hidden_items() {
+        let mut todomvc = todo_with_items(&["a", "b", "c", "d"]);
+        let ids: Vec<_> = todomvc.items.iter().map(|item| item.id).collect();
+        todomvc.update(Message::ToggleTodo(ids[1]), &mut Commands::default());
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+
+        let visible = |todomvc: &Todo| -> String {
+            todomvc.visible_items().iter().map(|item| item.text.as_str()).collect()
+        };
+        assert_eq!(visible(&todomvc), "acd");
+
+        // dragging "d" onto "a" (or pressing alt+up twice) puts it first, the hidden "b" keeps
+        // its place relative to the others
+        todomvc.update(Message::MoveTodo { from: ids[3], to: ids[0] }, &mut Commands::default());
+        assert_eq!(visible(&todomvc), "dac");
+        assert_eq!(texts(&todomvc.items), vec!["d", "a", "b", "c"]);
+
+        // alt+down on "a" moves it past the next visible item, skipping the hidden one
+        todomvc.update(Message::MoveTodo { from: ids[0], to: ids[2] }, &mut Commands::default());
+        assert_eq!(visible(&todomvc), "dca");
+        assert_eq!(texts(&todomvc.items), vec!["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut todomvc = todo_with_items(&["a", "b", "c"]);
+        let ids: Vec<_> = todomvc.items.iter().map(|item| item.id).collect();
+        todomvc.update(Message::AddSubtask(ids[0], "a.1".to_owned()), &mut Commands::default());
+        let subtask = todomvc.items[0].children[0].id;
+
+        // nothing is selected at first
+        todomvc.update(Message::ToggleAtCursor, &mut Commands::default());
+        assert!(todomvc.items.iter().all(|item| !item.is_complete));
+
+        // the cursor walks through visible items and subtasks, stopping at the ends
+        for &expected in &[ids[0], subtask, ids[1], ids[2], ids[2]] {
+            todomvc.update(Message::CursorDown, &mut Commands::default());
+            assert_eq!(todomvc.cursor, Some(expected));
+        }
+        for &expected in &[ids[1], subtask, ids[0], ids[0]] {
+            todomvc.update(Message::CursorUp, &mut Commands::default());
+            assert_eq!(todomvc.cursor, Some(expected));
+        }
+
+        // hidden items are skipped
+        todomvc.update(Message::ToggleTodo(ids[1]), &mut Commands::default());
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+        todomvc.update(Message::CursorDown, &mut Commands::default());
+        todomvc.update(Message::CursorDown, &mut Commands::default());
+        assert_eq!(todomvc.cursor, Some(ids[2]));
+
+        // starting from the bottom when the selected item is hidden
+        todomvc.cursor = Some(ids[1]);
+        todomvc.update(Message::CursorUp, &mut Commands::default());
+        assert_eq!(todomvc.cursor, Some(ids[2]));
+    }
+
+    #[test]
+    fn cursor_actions() {
+        let mut todomvc = todo_with_items(&["a", "b", "c"]);
+        let ids: Vec<_> = todomvc.items.iter().map(|item| item.id).collect();
+        todomvc.update(Message::CursorDown, &mut Commands::default());
+        todomvc.update(Message::CursorDown, &mut Commands::default());
+
+        todomvc.update(Message::ToggleAtCursor, &mut Commands::default());
+        assert!(todomvc.items[1].is_complete);
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::EditAtCursor, &mut cmds);
+        assert_eq!(todomvc.pending_edit, Some((ids[1], "b".to_owned())));
+        assert!(cmds.post_render.iter().any(|cmd| matches!(cmd, Command::FocusEdit)));
+        todomvc.update(Message::AbortEdit, &mut Commands::default());
+
+        // the cursor moves on to the next item, or the previous one at the end
+        todomvc.update(Message::RemoveAtCursor, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), vec!["a", "c"]);
+        assert_eq!(todomvc.cursor, Some(ids[2]));
+        todomvc.update(Message::RemoveAtCursor, &mut Commands::default());
+        assert_eq!(todomvc.cursor, Some(ids[0]));
+        todomvc.update(Message::RemoveAtCursor, &mut Commands::default());
+        assert_eq!(todomvc.cursor, None);
+        assert!(todomvc.items.is_empty());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::FocusNewTodo, &mut cmds);
+        assert!(cmds.immediate.iter().any(|cmd| matches!(cmd, Command::FocusPending)));
+    }
+
+    #[test]
+    fn search() {
+        let mut todomvc = todo_with_items(&["Buy milk", "Walk the dog", "buy bread"]);
+        let visible = |todomvc: &Todo| -> Vec<String> {
+            todomvc.visible_items().iter().map(|item| item.text.clone()).collect()
+        };
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::UpdateSearch("BUY".to_owned()), &mut cmds);
+        assert_eq!(visible(&todomvc), vec!["Buy milk", "buy bread"]);
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::ReplaceHistory(route) => route.to_url() == "#/?q=BUY",
+            _ => false,
+        }));
+
+        // searches combine with filters
+        todomvc.update(Message::ToggleTodo(todomvc.items[0].id), &mut Commands::default());
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+        assert_eq!(visible(&todomvc), vec!["buy bread"]);
+        assert_eq!(todomvc.route().to_url(), "#/active?q=BUY");
+
+        todomvc.update(Message::Navigate(AppRoute::parse("#/?q=wlk%20dg").unwrap()), &mut Commands::default());
+        assert!(visible(&todomvc).is_empty());
+        todomvc.update(Message::SetFuzzySearch(true), &mut Commands::default());
+        assert_eq!(visible(&todomvc), vec!["Walk the dog"]);
+        todomvc.update(Message::SetFuzzySearch(false), &mut Commands::default());
+        assert!(visible(&todomvc).is_empty());
+    }
+
+    #[test]
+    fn search_subtasks() {
+        let mut todomvc = todo_with_subtasks();
+        let children = |todomvc: &Todo| -> String {
+            todomvc.visible_children(&todomvc.items[0]).iter().map(|item| item.text.as_str()).collect()
+        };
+
+        // parents of matching subtasks are shown with just the matching subtasks
+        todomvc.update(Message::UpdateSearch("b".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.visible_items().len(), 1);
+        assert_eq!(children(&todomvc), "b");
+
+        // all the subtasks of matching items are shown
+        todomvc.update(Message::UpdateSearch("parent".to_owned()), &mut Commands::default());
+        assert_eq!(children(&todomvc), "abc");
+    }
+
+    #[test]
+    fn tags() {
+        let mut todomvc = Todo::default();
+
+        todomvc.update(Message::UpdatePending("write #Work report #work #q3 about #".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+        todomvc.update(Message::UpdatePending("buy milk #home".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+
+        assert_eq!(todomvc.items[0].text, "write report about #");
+        assert_eq!(todomvc.items[0].tags.iter().collect::<Vec<_>>(), vec!["q3", "work"]);
+        assert_eq!(todomvc.tags().into_iter().collect::<Vec<_>>(), vec!["home", "q3", "work"]);
+
+        // editing shows the tags and saving replaces them
+        let id = todomvc.items[0].id;
+        todomvc.update(Message::EditTodo(id), &mut Commands::default());
+        assert_eq!(todomvc.pending_edit, Some((id, "write report about # #q3 #work".to_owned())));
+        todomvc.update(Message::UpdateEdit("write report #home".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(todomvc.items[0].text, "write report");
+        assert_eq!(todomvc.items[0].tags.iter().collect::<Vec<_>>(), vec!["home"]);
+    }
+
+    #[test]
+    fn tags_without_text() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+
+        // only tags, or nothing at all, doesn't add an item
+        for text in &["#x", " #x #y ", "", "   "] {
+            todomvc.update(Message::UpdatePending(text.to_string()), &mut Commands::default());
+            let mut cmds = Commands::default();
+            todomvc.update(Message::AddTodo, &mut cmds);
+            assert!(cmds.immediate.is_empty(), "adding {:?}", text);
+        }
+        assert_eq!(texts(&todomvc.items), ["a", "b"]);
+
+        // and saving an edit down to only tags moves the item to the trash, like saving it empty
+        todomvc.update(Message::EditTodo(1), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit("#y".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["b"]);
+        assert_eq!(texts(&todomvc.trash), ["a"]);
+    }
+
+    #[test]
+    fn tag_filter() {
+        let mut todomvc = Todo::default();
+        for text in &["one #work", "two #home", "three #work"] {
+            todomvc.update(Message::UpdatePending(text.to_string()), &mut Commands::default());
+            todomvc.update(Message::AddTodo, &mut Commands::default());
+        }
+        todomvc.update(Message::ToggleTodo(todomvc.items[0].id), &mut Commands::default());
+
+        let visible = |todomvc: &Todo| -> Vec<String> {
+            todomvc.visible_items().iter().map(|item| item.text.clone()).collect()
+        };
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowTag(Some("work".to_owned())), &mut cmds);
+        assert_eq!(visible(&todomvc), vec!["one", "three"]);
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::PushHistory(route) => route.to_url() == "#/tag/work",
+            _ => false,
+        }));
+
+        // tags combine with filters
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowActive(true), &mut cmds);
+        assert_eq!(visible(&todomvc), vec!["three"]);
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::PushHistory(route) => route.to_url() == "#/tag/work/active",
+            _ => false,
+        }));
+
+        todomvc.update(Message::Navigate(AppRoute::parse("#/tag/home").unwrap()), &mut Commands::default());
+        assert_eq!(visible(&todomvc), vec!["two"]);
+        assert_eq!(todomvc.filter, Filter::All);
+
+        todomvc.update(Message::ShowTag(None), &mut Commands::default());
+        assert_eq!(visible(&todomvc).len(), 3);
+    }
+
+    #[test]
+    fn abort_edit_does_not_modify() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            text: "text".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::EditTodo(0), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit(" edited text  ".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AbortEdit, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].text, "text");
+    }
+
+    #[test]
+    fn clear_completed() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            text: "text1".to_owned(),
+            .. Item::default()
+        });
+        todomvc.items.push(Item {
+            text: "text2".to_owned(),
+            is_complete: true,
+            .. Item::default()
+        });
+        todomvc.items.push(Item {
+            text: "text3".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::ClearCompleted, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 2);
+        assert_eq!(todomvc.items[0].text, "text1");
+        assert_eq!(todomvc.items[1].text, "text3");
+        assert_eq!(texts(&todomvc.archive), ["text2"]);
+    }
+
+    #[test]
+    fn archive() {
+        let mut todomvc = todo_with_items(&["a", "b", "c"]);
+        todomvc.update(Message::ToggleTodo(1), &mut Commands::default());
+        todomvc.update(Message::ToggleTodo(3), &mut Commands::default());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ClearCompleted, &mut cmds);
+        persist(cmds);
+        assert_eq!(texts(&todomvc.items), ["b"]);
+        assert_eq!(texts(&todomvc.archive), ["a", "c"]);
+
+        // the archive is persisted next to the items
+        let archive = read_items_from_storage(&*todomvc.store, "todo-euca-archive");
+        assert_eq!(archive, todomvc.archive);
+        let reloaded = Todo::with_store(todomvc.store.clone());
+        assert_eq!(reloaded.archive, todomvc.archive);
+
+        // undo brings items back from the archive, redo archives them again
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["a", "b", "c"]);
+        assert!(todomvc.archive.is_empty());
+        todomvc.update(Message::Redo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.archive), ["a", "c"]);
+
+        todomvc.update(Message::RestoreArchived(3), &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["b", "c"]);
+        assert!(todomvc.items[1].is_complete);
+        assert_eq!(texts(&todomvc.archive), ["a"]);
+
+        todomvc.update(Message::DeleteArchived(1), &mut Commands::default());
+        assert!(todomvc.archive.is_empty());
+        assert!(todomvc.toast.as_ref().unwrap().undoable);
+
+        // ids of archived items aren't reused
+        todomvc.update(Message::Undo, &mut Commands::default());
+        todomvc.update(Message::UpdatePending("d".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+        assert_eq!(todomvc.items.last().unwrap().id, 4);
+    }
+
+    #[test]
+    fn archive_by_date() {
+        let now = clock::FixedClock { today: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap() }.now();
+        let mut todomvc = Todo::default();
+        for (id, completed_at) in [
+            (1, Some(now - chrono::Duration::days(1))),
+            (2, None),
+            (3, Some(now)),
+            (4, Some(now - chrono::Duration::days(1) + chrono::Duration::minutes(5))),
+        ] {
+            todomvc.archive.push(Item { id, is_complete: true, completed_at, .. Item::default() });
+        }
+
+        let groups: Vec<(Option<NaiveDate>, Vec<ItemId>)> = todomvc.archive_by_date().into_iter()
+            .map(|(date, items)| (date, items.iter().map(|item| item.id).collect()))
+            .collect();
+        assert_eq!(groups, [
+            (NaiveDate::from_ymd_opt(2024, 3, 15), vec![3]),
+            (NaiveDate::from_ymd_opt(2024, 3, 14), vec![4, 1]),
+            (None, vec![2]),
+        ]);
+    }
+
+    #[test]
+    fn auto_archive() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let clock = Rc::new(clock::FixedClock { today });
+        let now = clock.now();
+        let mut todomvc = Todo::with_items(vec![
+            Item { id: 1, is_complete: true, completed_at: Some(now - chrono::Duration::days(10)), .. Item::default() },
+            Item { id: 2, is_complete: true, completed_at: Some(now - chrono::Duration::days(2)), .. Item::default() },
+            Item { id: 3, is_complete: true, .. Item::default() },
+            Item { id: 4, .. Item::default() },
+        ]).with_clock(clock);
+
+        todomvc.update(Message::AutoArchive(7), &mut Commands::default());
+        assert_eq!(todomvc.archive.iter().map(|item| item.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(todomvc.items.len(), 3);
+
+        // nothing left to archive
+        let mut cmds = Commands::default();
+        todomvc.update(Message::AutoArchive(7), &mut cmds);
+        assert!(cmds.immediate.is_empty());
+    }
+
+    #[test]
+    fn archive_view() {
+        let mut todomvc = Todo::default();
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowView(View::Archive), &mut cmds);
+        assert!(matches!(&cmds.immediate[..], [Command::PushHistory(route)] if route.to_url() == "#/archive"));
+
+        todomvc.update(Message::Navigate(AppRoute::default()), &mut Commands::default());
+        assert_eq!(todomvc.view, View::Items);
+        todomvc.update(Message::Navigate(AppRoute::parse("#/archive").unwrap()), &mut Commands::default());
+        assert_eq!(todomvc.view, View::Archive);
+    }
+
+    #[test]
+    fn trash() {
+        let mut todomvc = todo_with_items(&["a", "b", "c"]);
+        todomvc.update(Message::AddSubtask(2, "d".to_owned()), &mut Commands::default());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::RemoveTodo(1), &mut cmds);
+        todomvc.update(Message::RemoveTodo(4), &mut cmds);
+        persist(cmds);
+        assert_eq!(texts(&todomvc.items), ["b", "c"]);
+        assert!(todomvc.items[0].children.is_empty());
+        assert_eq!(texts(&todomvc.trash), ["a", "d"]);
+        assert!(todomvc.trash.iter().all(|item| item.deleted_at.is_some()));
+
+        // the trash is persisted next to the items
+        let trash = read_items_from_storage(&*todomvc.store, "todo-euca-trash");
+        assert_eq!(trash, todomvc.trash);
+        let reloaded = Todo::with_store(todomvc.store.clone());
+        assert_eq!(reloaded.trash, todomvc.trash);
+
+        // undo takes the item back out of the trash
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items[0].children), ["d"]);
+        assert_eq!(texts(&todomvc.trash), ["a"]);
+        todomvc.update(Message::Redo, &mut Commands::default());
+
+        // restored subtasks come back at the top level
+        todomvc.update(Message::RestoreTrashed(4), &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["b", "c", "d"]);
+        assert_eq!(todomvc.items[2].deleted_at, None);
+        assert_eq!(texts(&todomvc.trash), ["a"]);
+
+        // clearing the text of an item while editing also moves it to the trash
+        todomvc.update(Message::EditTodo(3), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit(" ".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(texts(&todomvc.trash), ["a", "c"]);
+
+        todomvc.update(Message::EmptyTrash, &mut Commands::default());
+        assert!(todomvc.trash.is_empty());
+        assert_eq!(todomvc.toast.as_ref().unwrap().message, "Deleted 2 items");
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.trash), ["a", "c"]);
+
+        // ids of removed items aren't reused
+        todomvc.update(Message::UpdatePending("e".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+        assert_eq!(todomvc.items.last().unwrap().id, 5);
+    }
+
+    #[test]
+    fn purge_trash() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let clock = Rc::new(clock::FixedClock { today });
+        let now = clock.now();
+        let mut todomvc = Todo::default().with_clock(clock);
+        todomvc.trash = vec![
+            Item { id: 1, deleted_at: Some(now - chrono::Duration::days(40)), .. Item::default() },
+            Item { id: 2, deleted_at: Some(now - chrono::Duration::days(2)), .. Item::default() },
+            Item { id: 3, .. Item::default() },
+        ];
+
+        todomvc.update(Message::PurgeTrash(30), &mut Commands::default());
+        assert_eq!(todomvc.trash.iter().map(|item| item.id).collect::<Vec<_>>(), [2, 3]);
+
+        // nothing left to purge
+        let mut cmds = Commands::default();
+        todomvc.update(Message::PurgeTrash(30), &mut cmds);
+        assert!(cmds.immediate.is_empty());
+    }
+
+    #[test]
+    fn toggle_all() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            text: "text1".to_owned(),
+            .. Item::default()
+        });
+        todomvc.items.push(Item {
+            text: "text2".to_owned(),
+            is_complete: true,
+            .. Item::default()
+        });
+        todomvc.items.push(Item {
+            text: "text3".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        assert!(todomvc.items.iter().all(|item| item.is_complete));
+
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        assert!(todomvc.items.iter().all(|item| !item.is_complete));
+    }
+
+    #[test]
+    fn due_filter() {
+        let today = NaiveDate::from_ymd_opt(2020, 3, 15).unwrap();
+        let mut todomvc = Todo::default().with_clock(Rc::new(clock::FixedClock { today }));
+
+        for (i, (due, is_complete)) in [
+            (Some(today.succ_opt().unwrap()), false),
+            (Some(today), false),
+            (None, false),
+            (Some(today.pred_opt().unwrap()), false),
+            (Some(today.pred_opt().unwrap()), true),
+        ].iter().enumerate() {
+            todomvc.items.push(Item {
+                id: i as ItemId + 1,
+                text: format!("text{}", i + 1),
+                due: *due,
+                is_complete: *is_complete,
+                .. Item::default()
+            });
+        }
+
+        todomvc.update(Message::ShowDue(false), &mut Commands::default());
+
+        // due today or overdue, earliest first
+        let visible: Vec<_> = todomvc.visible_items().iter().map(|item| item.id).collect();
+        assert_eq!(visible, vec![4, 2]);
+
+        assert!(todomvc.items[3].is_overdue(today));
+        assert!(!todomvc.items[1].is_overdue(today));
+        assert!(!todomvc.items[4].is_overdue(today));
+    }
+
+    #[test]
+    fn sort_items() {
+        let mut todomvc = Todo::default();
+        for (text, priority, is_complete) in &[
+            ("b", Priority::Low, true),
+            ("C", Priority::High, false),
+            ("a", Priority::None, false),
+            ("d", Priority::High, true),
+        ] {
+            let id = todomvc.new_id();
+            todomvc.items.push(Item {
+                id,
+                text: text.to_string(),
+                priority: *priority,
+                is_complete: *is_complete,
+                .. Item::default()
+            });
+        }
+
+        let visible = |todomvc: &Todo| -> String {
+            todomvc.visible_items().iter().map(|item| item.text.as_str()).collect()
+        };
+
+        assert_eq!(visible(&todomvc), "bCad");
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::SortBy(SortMode::Priority), &mut cmds);
+        assert_eq!(visible(&todomvc), "Cdba");
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::PushHistory(route) => route.to_url() == "#/?sort=priority",
+            _ => false,
+        }));
+
+        todomvc.update(Message::SortBy(SortMode::Alphabetical), &mut Commands::default());
+        assert_eq!(visible(&todomvc), "abCd");
+
+        todomvc.update(Message::SortBy(SortMode::Completion), &mut Commands::default());
+        assert_eq!(visible(&todomvc), "Cabd");
+
+        // sorting combines with filters and doesn't change the underlying order
+        todomvc.update(Message::ShowActive(false), &mut Commands::default());
+        todomvc.update(Message::SortBy(SortMode::Priority), &mut Commands::default());
+        assert_eq!(visible(&todomvc), "Ca");
+        assert_eq!(todomvc.items[0].text, "b");
+
+        // handlers address items by id regardless of the displayed order
+        let id = todomvc.visible_items()[0].id;
+        todomvc.update(Message::ToggleTodo(id), &mut Commands::default());
+        assert!(todomvc.items[1].is_complete);
+    }
+
+    #[test]
+    fn cycle_priority() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item::default());
+
+        for &priority in &[Priority::Low, Priority::Medium, Priority::High, Priority::None] {
+            todomvc.update(Message::CyclePriority(0), &mut Commands::default());
+            assert_eq!(todomvc.items[0].priority, priority);
+        }
+    }
+
+    #[test]
+    fn navigate() {
+        let mut todomvc = Todo::default();
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Navigate(AppRoute::parse("#/completed?sort=alphabetical").unwrap()), &mut cmds);
+
+        assert_eq!(todomvc.filter, Filter::Completed);
+        assert_eq!(todomvc.sort, SortMode::Alphabetical);
+        assert!(cmds.immediate.is_empty());
+
+        // changing filters keeps the sort order
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowActive(true), &mut cmds);
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::PushHistory(route) => route.to_url() == "#/active?sort=alphabetical",
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn history_entries() {
+        let history = |cmds: &Commands| -> Vec<(bool, String)> {
+            cmds.immediate.iter().filter_map(|cmd| match cmd {
+                Command::PushHistory(route) => Some((true, route.to_url())),
+                Command::ReplaceHistory(route) => Some((false, route.to_url())),
+                _ => None,
+            }).collect()
+        };
+        let mut todomvc = todo_with_items(&["a"]);
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowActive(true), &mut cmds);
+        assert_eq!(history(&cmds), [(true, "#/active".to_owned())]);
+
+        // selecting the current filter again doesn't add an entry
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ShowActive(true), &mut cmds);
+        assert!(history(&cmds).is_empty());
+
+        // neither does navigating, the browser already did
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Navigate(AppRoute::default()), &mut cmds);
+        todomvc.update(Message::ShowAll(true), &mut cmds);
+        assert!(history(&cmds).is_empty());
+
+        // searching updates the current entry
+        let mut cmds = Commands::default();
+        todomvc.update(Message::UpdateSearch("a".to_owned()), &mut cmds);
+        todomvc.update(Message::UpdateSearch("".to_owned()), &mut cmds);
+        assert_eq!(history(&cmds), [(false, "#/?q=a".to_owned()), (false, "#/".to_owned())]);
+
+        // editing gets its own entry, which is left by going back once the edit is done, so it
+        // doesn't leave a second entry for the list behind
+        let popped = |cmds: &Commands| cmds.immediate.iter().any(|cmd| matches!(cmd, Command::PopHistory));
+        for done in [Message::SaveEdit, Message::AbortEdit].iter().cloned() {
+            let mut cmds = Commands::default();
+            todomvc.update(Message::EditTodo(1), &mut cmds);
+            assert_eq!(history(&cmds), [(true, "#/item/1/edit".to_owned())]);
+
+            let mut cmds = Commands::default();
+            todomvc.update(done, &mut cmds);
+            assert!(history(&cmds).is_empty());
+            assert!(popped(&cmds));
+            assert_eq!(todomvc.location.to_url(), "#/");
+        }
+
+        // going back during an edit ends it, going forward again restores it
+        let mut cmds = Commands::default();
+        todomvc.update(Message::EditTodo(1), &mut cmds);
+        todomvc.update(Message::UpdateEdit("b".to_owned()), &mut cmds);
+        todomvc.update(Message::Navigate(AppRoute::default()), &mut cmds);
+        assert_eq!(todomvc.pending_edit, None);
+        todomvc.update(Message::Navigate(AppRoute::parse("#/item/1/edit").unwrap()), &mut cmds);
+        assert_eq!(todomvc.pending_edit, Some((1, "a".to_owned())));
+        assert_eq!(history(&cmds), [(true, "#/item/1/edit".to_owned())]);
+
+        // an edit opened by the browser already has its entry, which is updated once it's done
+        let mut cmds = Commands::default();
+        todomvc.update(Message::SaveEdit, &mut cmds);
+        assert_eq!(history(&cmds), [(false, "#/".to_owned())]);
+        assert!(!popped(&cmds));
+
+        // unknown routes are replaced with the default one
+        let mut cmds = Commands::default();
+        todomvc.update(Message::UnknownRoute("#/bogus".to_owned()), &mut cmds);
+        assert_eq!(history(&cmds), [(false, "#/".to_owned())]);
+    }
+
+    #[test]
+    fn title() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        assert_eq!(todomvc.title("Todos"), "(2) Todos");
+
+        todomvc.update(Message::ShowActive(true), &mut Commands::default());
+        assert_eq!(todomvc.title("Todos"), "(2) Active • Todos");
+
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        todomvc.update(Message::ShowCompleted(true), &mut Commands::default());
+        assert_eq!(todomvc.title("Todos"), "Completed • Todos");
+    }
+
+    #[test]
+    fn navigate_to_item() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+
+        todomvc.update(Message::Navigate(AppRoute::parse("#/item/1/active").unwrap()), &mut Commands::default());
+        assert_eq!(todomvc.cursor, Some(1));
+        assert_eq!(todomvc.filter, Filter::Active);
+        // the item isn't part of the route of what's shown
+        assert_eq!(todomvc.route().to_url(), "#/active");
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Navigate(AppRoute::parse("#/item/99").unwrap()), &mut cmds);
+        assert_eq!(todomvc.cursor, Some(1));
+        assert!(cmds.post_render.is_empty());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Navigate(AppRoute::parse("#/item/2/edit").unwrap()), &mut cmds);
+        assert_eq!(todomvc.cursor, Some(2));
+        assert_eq!(todomvc.pending_edit, Some((2, "b".to_owned())));
+        assert!(matches!(cmds.post_render[..], [Command::ScrollTo(2), Command::FocusEdit]));
+    }
+
+    #[test]
+    fn copy_link() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        todomvc.update(Message::ShowActive(true), &mut Commands::default());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::CopyLink(1), &mut cmds);
+        assert!(matches!(&cmds.immediate[..], [Command::CopyLink(url)] if url == "#/item/1/active"));
+    }
+
+    #[test]
+    fn unknown_route() {
+        let mut todomvc = Todo::default();
+        todomvc.update(Message::ShowActive(true), &mut Commands::default());
+
+        todomvc.update(Message::UnknownRoute("#/bogus".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.filter, Filter::All);
+        assert!(!todomvc.toast.unwrap().undoable);
+    }
+
+    #[test]
+    fn set_due() {
+        let due = NaiveDate::from_ymd_opt(2020, 3, 15);
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item::default());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::SetDue(0, due), &mut cmds);
+        assert_eq!(todomvc.items[0].due, due);
+        assert!(cmds.immediate.iter().any(|cmd| matches!(cmd, Command::UpdateStorage(..))));
+
+        let time = NaiveTime::from_hms_opt(9, 30, 0);
+        todomvc.update(Message::SetDueTime(0, time), &mut Commands::default());
+        assert_eq!(todomvc.items[0].due_time, time);
+
+        // the time goes along with the date
+        todomvc.update(Message::SetDue(0, None), &mut Commands::default());
+        assert_eq!(todomvc.items[0].due, None);
+        assert_eq!(todomvc.items[0].due_time, None);
+        todomvc.update(Message::SetDueTime(0, time), &mut Commands::default());
+        assert_eq!(todomvc.items[0].due_time, None);
+    }
+
+    #[test]
+    fn quick_add() {
+        let clock = Rc::new(clock::FixedClock { today: NaiveDate::from_ymd_opt(2024, 3, 13).unwrap() });
+        let mut todomvc = Todo::default().with_clock(clock);
+
+        todomvc.update(Message::UpdatePending("Call vendor tomorrow 3pm !high #ops".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.pending_quick_add().preview(todomvc.today()), ["Due tomorrow at 3:00 PM", "Priority: high", "#ops"]);
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+
+        let item = &todomvc.items[0];
+        assert_eq!(item.text, "Call vendor");
+        assert_eq!(item.due, NaiveDate::from_ymd_opt(2024, 3, 14));
+        assert_eq!(item.due_time, NaiveTime::from_hms_opt(15, 0, 0));
+        assert_eq!(item.priority, Priority::High);
+        assert_eq!(item.tags.iter().collect::<Vec<_>>(), ["ops"]);
+        assert!(todomvc.pending_quick_add().preview(todomvc.today()).is_empty());
+
+        // editing keeps the words as they are
+        todomvc.update(Message::EditTodo(1), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit("Call vendor today".to_owned()), &mut Commands::default());
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(todomvc.items[0].text, "Call vendor today");
+        assert_eq!(todomvc.items[0].due, NaiveDate::from_ymd_opt(2024, 3, 14));
+    }
+
+    #[test]
+    fn quick_add_without_text() {
+        let mut todomvc = Todo::default();
+
+        // tags alone leave nothing to call the item, so it waits for some text
+        todomvc.update(Message::UpdatePending("#ops".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.pending_quick_add().text, "");
+        let mut cmds = Commands::default();
+        todomvc.update(Message::AddTodo, &mut cmds);
+        assert!(todomvc.items.is_empty());
+        assert!(cmds.immediate.is_empty());
+        assert_eq!(todomvc.pending_item, "#ops");
+
+        todomvc.update(Message::UpdatePending("#ops call vendor".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["call vendor"]);
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut todomvc = Todo::default();
+
+        for text in &["item1", "item2"] {
+            todomvc.update(Message::UpdatePending(text.to_string()), &mut Commands::default());
+            todomvc.update(Message::AddTodo, &mut Commands::default());
+        }
+        todomvc.update(Message::ToggleTodo(todomvc.items[0].id), &mut Commands::default());
+        todomvc.update(Message::ClearCompleted, &mut Commands::default());
+
+        assert_eq!(todomvc.items.len(), 1);
+        assert!(todomvc.toast.is_some());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Undo, &mut cmds);
+        assert_eq!(todomvc.items.len(), 2);
+        assert!(todomvc.items[0].is_complete);
+        assert!(todomvc.toast.is_none());
+        assert!(cmds.immediate.iter().any(|cmd| matches!(cmd, Command::UpdateStorage(..))));
+
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert!(!todomvc.items[0].is_complete);
+
+        todomvc.update(Message::Redo, &mut Commands::default());
+        todomvc.update(Message::Redo, &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].text, "item2");
+
+        // nothing left to redo
+        todomvc.update(Message::Redo, &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 1);
+
+        // a new change discards the redo stack
+        todomvc.update(Message::Undo, &mut Commands::default());
+        todomvc.update(Message::ToggleAll, &mut Commands::default());
+        todomvc.update(Message::Redo, &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 2);
+        assert!(todomvc.items.iter().all(|item| item.is_complete));
+    }
+
+    #[test]
+    fn undo_history_is_bounded() {
+        let mut todomvc = Todo::default();
+
+        for i in 0..HISTORY_LIMIT + 10 {
+            todomvc.update(Message::UpdatePending(i.to_string()), &mut Commands::default());
+            todomvc.update(Message::AddTodo, &mut Commands::default());
+        }
+
+        for _ in 0..HISTORY_LIMIT + 10 {
+            todomvc.update(Message::Undo, &mut Commands::default());
+        }
+
+        assert_eq!(todomvc.items.len(), 10);
+    }
+
+    #[test]
+    fn import_assigns_new_ids() {
+        let mut todomvc = Todo::default();
+        todomvc.update(Message::UpdatePending("existing".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddTodo, &mut Commands::default());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Imported(Format::Markdown, "- [x] one\n- [ ] two\n".to_owned()), &mut cmds);
+
+        assert_eq!(todomvc.items.len(), 3);
+        assert_eq!(todomvc.items[1].text, "one");
+        assert!(todomvc.items[1].is_complete);
+        let mut ids: Vec<_> = todomvc.items.iter().map(|item| item.id).collect();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+        assert!(cmds.immediate.iter().any(|cmd| matches!(cmd, Command::UpdateStorage(..))));
+
+        // importing can be undone
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 1);
+    }
+
+    #[test]
+    fn import_subtasks() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        todomvc.update(Message::AddSubtask(2, "b.1".to_owned()), &mut Commands::default());
+        todomvc.update(Message::AddSubtask(2, "b.2".to_owned()), &mut Commands::default());
+
+        let data = export::export(&todomvc.items, Format::Json);
+        todomvc.update(Message::Imported(Format::Json, data), &mut Commands::default());
+
+        fn ids(items: &[Item]) -> Vec<ItemId> {
+            items.iter().flat_map(|item| std::iter::once(item.id).chain(ids(&item.children))).collect()
+        }
+        let mut all = ids(&todomvc.items);
+        assert_eq!(all.len(), 8);
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 8);
+
+        // the copies keep their text and subtasks
+        assert_eq!(texts(&todomvc.items), ["a", "b", "a", "b"]);
+        assert_eq!(texts(&todomvc.items[3].children), ["b.1", "b.2"]);
+
+        let copy = todomvc.items[3].children[0].id;
+        todomvc.update(Message::ToggleTodo(copy), &mut Commands::default());
+        assert!(todomvc.items[3].children[0].is_complete);
+        assert!(!todomvc.items[3].children[1].is_complete);
+        assert!(!todomvc.items[1].children[0].is_complete);
+    }
+
+    #[test]
+    fn import_errors_leave_items_alone() {
+        let mut todomvc = Todo::default();
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::Imported(Format::Json, "not json".to_owned()), &mut cmds);
+
+        assert!(todomvc.items.is_empty());
+        assert!(cmds.immediate.is_empty());
+        assert!(!todomvc.toast.unwrap().undoable);
+    }
+
+    #[test]
+    fn reload_items() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            id: 1,
+            text: "text1".to_owned(),
+            .. Item::default()
+        });
+        todomvc.items.push(Item {
+            id: 2,
+            text: "text2".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::EditTodo(2), &mut Commands::default());
+        todomvc.update(Message::UpdateEdit("edited".to_owned()), &mut Commands::default());
+
+        // another tab removes the first item and adds a new one
+        let mut cmds = Commands::default();
+        todomvc.update(Message::ItemsReloaded(STORAGE_KEY.to_owned(), vec![
+            Item {
+                id: 2,
+                text: "text2".to_owned(),
+                .. Item::default()
+            },
+            Item {
+                id: 7,
+                text: "text7".to_owned(),
+                .. Item::default()
+            },
+        ]), &mut cmds);
+
+        // reloading doesn't write back to storage
+        assert!(cmds.immediate.is_empty());
+
+        assert_eq!(todomvc.items.len(), 2);
+        assert_eq!(todomvc.pending_edit, Some((2, "edited".to_owned())));
+        assert!(todomvc.new_id() > 7);
+
+        todomvc.update(Message::SaveEdit, &mut Commands::default());
+        assert_eq!(todomvc.items[0].text, "edited");
+    }
+
+    #[test]
+    fn reload_drops_edit_of_removed_item() {
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item {
+            id: 1,
+            text: "text1".to_owned(),
+            .. Item::default()
+        });
+
+        todomvc.update(Message::EditTodo(1), &mut Commands::default());
+        todomvc.update(Message::ItemsReloaded(STORAGE_KEY.to_owned(), vec![]), &mut Commands::default());
+
+        assert!(todomvc.items.is_empty());
+        assert!(todomvc.pending_edit.is_none());
+
+        // the reload can't be undone
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert!(todomvc.items.is_empty());
+    }
+
+    #[test]
+    fn reload_drops_undo_toast() {
+        let mut todomvc = todo_with_items(&["a", "b"]);
+        todomvc.update(Message::RemoveTodo(1), &mut Commands::default());
+        assert!(todomvc.toast.as_ref().unwrap().undoable);
+
+        // another tab adds an item, so undoing here would drop it again
+        let items = vec![todomvc.items[0].clone(), Item { id: 5, text: "c".to_owned(), .. Item::default() }];
+        todomvc.update(Message::ItemsReloaded(STORAGE_KEY.to_owned(), items), &mut Commands::default());
+        assert!(todomvc.toast.is_none());
+
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(texts(&todomvc.items), ["b", "c"]);
+    }
+
+    #[test]
+    fn storage_round_trip() {
+        let store: Rc<dyn TodoStore> = Rc::new(MemoryStore::default());
+        let mut todomvc = Todo::with_store(store.clone());
+        assert!(todomvc.items.is_empty());
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::UpdatePending("item".to_owned()), &mut cmds);
+        todomvc.update(Message::AddTodo, &mut cmds);
+
+        // persist the data the way the UpdateStorage command would
+        for cmd in cmds.immediate {
+            if let Command::UpdateStorage(store, key, data) = cmd {
+                store.save(&key, &data);
+            }
+        }
+
+        let todomvc = Todo::with_store(store.clone());
+        assert_eq!(todomvc.items.len(), 1);
+        assert_eq!(todomvc.items[0].text, "item");
+
+        store.clear(STORAGE_KEY);
+        assert!(Todo::with_store(store).items.is_empty());
+    }
+
+    /// Process storage commands the way the web app would.
+    fn persist(cmds: Commands) {
+        for cmd in cmds.immediate {
+            match cmd {
+                Command::UpdateStorage(store, key, data) => store.save(&key, &data),
+                Command::ClearStorage(store, key) => store.clear(&key),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn named_lists() {
+        let store: Rc<dyn TodoStore> = Rc::new(MemoryStore::default());
+        let mut todomvc = Todo::with_store(store.clone());
+        assert_eq!(todomvc.current_list().key, STORAGE_KEY);
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::UpdatePending("first".to_owned()), &mut cmds);
+        todomvc.update(Message::AddTodo, &mut cmds);
+        todomvc.update(Message::CreateList("  work ".to_owned()), &mut cmds);
+        assert!(todomvc.items.is_empty());
+        todomvc.update(Message::UpdatePending("report".to_owned()), &mut cmds);
+        todomvc.update(Message::AddTodo, &mut cmds);
+        assert!(cmds.immediate.iter().any(|cmd| match cmd {
+            Command::PushHistory(route) => route.to_url() == "#/list/work",
+            _ => false,
+        }));
+        persist(cmds);
+
+        // each list is stored under its own key
+        assert_eq!(todomvc.current_list().name, "work");
+        assert!(store.load(STORAGE_KEY).unwrap().contains("first"));
+        assert!(store.load(&todomvc.current_list().key).unwrap().contains("report"));
+
+        // lists are restored, starting with the first one
+        let mut todomvc = Todo::with_store(store.clone());
+        assert_eq!(todomvc.lists.iter().map(|list| list.name.as_str()).collect::<Vec<_>>(), vec!["Todos", "work"]);
+        assert_eq!(todomvc.items[0].text, "first");
+
+        todomvc.update(Message::SwitchList("work".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.items[0].text, "report");
+
+        // undo doesn't cross lists
+        todomvc.update(Message::Undo, &mut Commands::default());
+        assert_eq!(todomvc.items[0].text, "report");
+
+        let mut cmds = Commands::default();
+        todomvc.update(Message::RenameList("work".to_owned(), "Todos".to_owned()), &mut cmds);
+        assert_eq!(todomvc.current_list().name, "work");
+        todomvc.update(Message::RenameList("work".to_owned(), "job".to_owned()), &mut cmds);
+        assert_eq!(todomvc.current_list().name, "job");
+        persist(cmds);
+        assert_eq!(Todo::with_store(store.clone()).lists[1].name, "job");
+
+        // deleting the current list switches to the first one
+        let key = todomvc.current_list().key.clone();
+        let mut cmds = Commands::default();
+        todomvc.update(Message::DeleteList("job".to_owned()), &mut cmds);
+        persist(cmds);
+        assert_eq!(todomvc.lists.len(), 1);
+        assert_eq!(todomvc.items[0].text, "first");
+        assert!(store.load(&key).is_none());
+
+        // the last list can't be deleted
+        todomvc.update(Message::DeleteList("Todos".to_owned()), &mut Commands::default());
+        assert_eq!(todomvc.lists.len(), 1);
+    }
+
+    #[test]
+    fn navigate_lists() {
+        let mut todomvc = Todo::default();
+        let mut cmds = Commands::default();
+        todomvc.update(Message::CreateList("home".to_owned()), &mut cmds);
+        todomvc.update(Message::UpdatePending("item".to_owned()), &mut cmds);
+        todomvc.update(Message::AddTodo, &mut cmds);
+        todomvc.update(Message::SwitchList("Todos".to_owned()), &mut cmds);
+        persist(cmds);
+
+        todomvc.update(Message::Navigate(AppRoute::parse("#/list/home/active").unwrap()), &mut Commands::default());
+        assert_eq!(todomvc.current_list().name, "home");
+        assert_eq!(todomvc.filter, Filter::Active);
+        assert_eq!(todomvc.route().to_url(), "#/list/home/active");
+
+        // reloads of other lists are ignored
+        todomvc.update(Message::ItemsReloaded(STORAGE_KEY.to_owned(), vec![]), &mut Commands::default());
+        assert_eq!(todomvc.items.len(), 1);
+
+        // unknown lists leave the current one alone
+        todomvc.update(Message::Navigate(AppRoute::parse("#/list/nowhere").unwrap()), &mut Commands::default());
+        assert_eq!(todomvc.current_list().name, "home");
+
+        todomvc.update(Message::Navigate(AppRoute::parse("#/").unwrap()), &mut Commands::default());
+        assert_eq!(todomvc.current_list().name, "Todos");
+    }
+
+    #[test]
+    fn unreadable_storage_is_backed_up() {
+        let store: Rc<dyn TodoStore> = Rc::new(MemoryStore::default());
+        store.save(STORAGE_KEY, "{garbage");
+
+        let todomvc = Todo::with_store(store.clone());
+
+        assert!(todomvc.items.is_empty());
+        assert_eq!(store.load(BACKUP_KEY).as_deref(), Some("{garbage"));
+    }
+
+    #[test]
+    fn storage_triggers() {
+        use Message::*;
+        use Command::*;
+
+        let mut todomvc = Todo::default();
+        todomvc.items.push(Item::default());
+        todomvc.items.push(Item::default());
+        todomvc.items.push(Item::default());
+
+        // ensure the following message types generate UpdateStorage commands
+        for msg in &[
+            AddTodo,
+            RemoveTodo(0),
+            ToggleTodo(0),
+            SaveEdit,
+            ClearCompleted,
+            ToggleAll,
+            ItemsChanged,
+        ] {
+            // do necessary prep work
+            match msg {
+                AddTodo => todomvc.update(UpdatePending("item".to_owned()), &mut Commands::default()),
+                SaveEdit => todomvc.update(EditTodo(0), &mut Commands::default()),
+                _ => {}
+            }
+
+            let mut cmds = Commands::default();
+            todomvc.update(msg.clone(), &mut cmds);
+
+            // verify the proper commands were generated
+            assert!(
+                cmds.immediate.iter().any(|cmd| match cmd {
+                    UpdateStorage(..) => true,
+                    _ => false,
+                }),
+                "didn't find UpdateStorage for {:?}", msg
+            );
+        }
+    }
+}
+
@@ -0,0 +1,470 @@
+//! The quick add syntax of the new todo input.
+//!
+//! Besides `#tags`, the text of a new item can give its due date, time and priority, as in
+//! `Call vendor tomorrow 3pm !high #ops`:
+//!
+//! - dates: `today`, `tomorrow`, `2024-03-15`, `next week`, `next month` and
+//!   `in 3 days|weeks|months`, optionally after `on`, `due` or `by`
+//! - weekdays: `on friday` (or `due`/`by friday`) for the next one, today included, and
+//!   `next friday` for the one a week after that
+//! - times: `3pm` and `9:30am`, or `15:00` and `noon` after `at` or `@`, for today unless a date is
+//!   given as well
+//! - priorities: `!low`, `!med` (or `!medium`) and `!high`
+//!
+//! Only the first date, time and priority are used, any others are left in the text. Words are
+//! matched regardless of case. Weekdays have to be spelled out and follow one of those words, and
+//! times need `am`/`pm` or `at`, so titles like "Friday Night Lights" or "Read John 3:16" are left
+//! alone.
+
+use std::collections::BTreeSet;
+use chrono::{Datelike,Duration,Months,NaiveDate,NaiveTime,Weekday};
+
+use super::item::{Priority,is_tag,parse_tags};
+
+/// What was made of the text of a new item.
+#[derive(Default,PartialEq,Debug)]
+pub struct QuickAdd {
+    /// The text without the parts that were recognized, with whitespace between words collapsed.
+    pub text: String,
+    pub tags: BTreeSet<String>,
+    pub due: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: Priority,
+}
+
+/// What a word means in the quick add syntax.
+#[derive(PartialEq,Debug)]
+enum Token {
+    Tag(String),
+    Priority(Priority),
+    /// A date on its own, like `tomorrow` or `2024-03-15`.
+    Date(NaiveDate),
+    /// A day of the week, only a date after `on` or `next`.
+    Weekday(Weekday),
+    /// A time on its own, like `3pm` or `@15:00`.
+    Time(NaiveTime),
+    /// A time like `15:00` or `noon`, only a time after `at`.
+    Clock(NaiveTime),
+    Number(u32),
+    Unit(Unit),
+    /// As in `next friday`.
+    Next,
+    /// As in `in 3 days`.
+    In,
+    /// `on`, `due` or `by`, before a date.
+    On,
+    /// `at` or `@`, before a time.
+    At,
+    /// Anything else.
+    Word,
+}
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+}
+
+impl Unit {
+    /// The date the given number of these after another one.
+    fn after(self, date: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Unit::Day => date.checked_add_signed(Duration::days(n.into())),
+            Unit::Week => date.checked_add_signed(Duration::weeks(n.into())),
+            Unit::Month => date.checked_add_months(Months::new(n)),
+        }
+    }
+}
+
+impl QuickAdd {
+    /// Parse the text of a new item, with relative dates counted from the given day.
+    ///
+    /// If nothing but dates, times and priorities is given, they are taken as the text of the
+    /// item instead, so an item can still be called "Today".
+    pub fn parse(input: &str, today: NaiveDate) -> Self {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let tokens: Vec<Token> = words.iter().map(|word| token(word, today)).collect();
+
+        let mut quick_add = QuickAdd::default();
+        let mut text = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            match quick_add.take_field(&tokens[i..], today) {
+                Some(n) => i += n,
+                None => {
+                    text.push(words[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        if text.is_empty() {
+            let (text, tags) = parse_tags(input);
+            return QuickAdd {
+                text,
+                tags,
+                .. QuickAdd::default()
+            };
+        }
+
+        quick_add.text = text.join(" ");
+        if quick_add.due_time.is_some() && quick_add.due.is_none() {
+            quick_add.due = Some(today);
+        }
+        quick_add
+    }
+
+    /// Fill in the field the given tokens start with, unless it was already given. Returns how
+    /// many tokens it took up.
+    fn take_field(&mut self, tokens: &[Token], today: NaiveDate) -> Option<usize> {
+        match &tokens[0] {
+            Token::Tag(tag) => {
+                self.tags.insert(tag.clone());
+                return Some(1);
+            }
+            Token::Priority(priority) if self.priority.is_none() => {
+                self.priority = *priority;
+                return Some(1);
+            }
+            _ => {}
+        }
+
+        if self.due.is_none() {
+            if let Some((due, n)) = date(tokens, today) {
+                self.due = Some(due);
+                return Some(n);
+            }
+        }
+
+        if self.due_time.is_none() {
+            if let Some((time, n)) = time(tokens) {
+                self.due_time = Some(time);
+                return Some(n);
+            }
+        }
+
+        None
+    }
+
+    /// Describe the recognized parts for showing while the item is typed, like
+    /// `["Due tomorrow at 3:00 PM", "Priority: high", "#ops"]`.
+    pub fn preview(&self, today: NaiveDate) -> Vec<String> {
+        let mut parts = vec![];
+
+        if let Some(due) = self.due {
+            let mut part = format!("Due {}", describe_date(due, today));
+            if let Some(time) = self.due_time {
+                part += &format!(" at {}", time.format("%-I:%M %p"));
+            }
+            parts.push(part);
+        }
+
+        if !self.priority.is_none() {
+            parts.push(format!("Priority: {}", self.priority.name()));
+        }
+
+        parts.extend(self.tags.iter().map(|tag| format!("#{}", tag)));
+        parts
+    }
+}
+
+fn token(word: &str, today: NaiveDate) -> Token {
+    if let Some(tag) = word.strip_prefix('#').map(str::to_ascii_lowercase) {
+        if is_tag(&tag) {
+            return Token::Tag(tag);
+        }
+    }
+
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "!low" => Token::Priority(Priority::Low),
+        "!med" | "!medium" => Token::Priority(Priority::Medium),
+        "!high" => Token::Priority(Priority::High),
+        "today" => Token::Date(today),
+        "tomorrow" => Token::Date(today.succ_opt().unwrap_or(today)),
+        "noon" => Token::Clock(NaiveTime::from_hms_opt(12, 0, 0).expect("noon is a valid time")),
+        "day" | "days" => Token::Unit(Unit::Day),
+        "week" | "weeks" => Token::Unit(Unit::Week),
+        "month" | "months" => Token::Unit(Unit::Month),
+        "next" => Token::Next,
+        "in" => Token::In,
+        "on" | "due" | "by" => Token::On,
+        "at" | "@" => Token::At,
+        "monday" => Token::Weekday(Weekday::Mon),
+        "tuesday" => Token::Weekday(Weekday::Tue),
+        "wednesday" => Token::Weekday(Weekday::Wed),
+        "thursday" => Token::Weekday(Weekday::Thu),
+        "friday" => Token::Weekday(Weekday::Fri),
+        "saturday" => Token::Weekday(Weekday::Sat),
+        "sunday" => Token::Weekday(Weekday::Sun),
+        _ => {
+            if let Ok(n) = word.parse() {
+                Token::Number(n)
+            }
+            else if let Some(time) = word.strip_prefix('@').and_then(parse_time) {
+                Token::Time(time)
+            }
+            else if let Some(time) = parse_time(&word) {
+                if word.ends_with("am") || word.ends_with("pm") {
+                    Token::Time(time)
+                }
+                else {
+                    Token::Clock(time)
+                }
+            }
+            else if let Ok(date) = NaiveDate::parse_from_str(&word, "%Y-%m-%d") {
+                Token::Date(date)
+            }
+            else {
+                Token::Word
+            }
+        }
+    }
+}
+
+/// Parse a lowercase time like `3pm`, `9:30am` or `15:00`.
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    let (clock, pm) = match (s.strip_suffix("am"), s.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => (s, None),
+    };
+
+    if clock.is_empty() || !clock.chars().all(|c| c.is_ascii_digit() || c == ':') {
+        return None;
+    }
+
+    // plain numbers are only times with am or pm
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if hour.len() <= 2 && minute.len() == 2 => (hour.parse::<u32>().ok()?, minute.parse().ok()?),
+        None if pm.is_some() && clock.len() <= 2 => (clock.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+
+    let hour = match pm {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None => hour,
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// The date the given tokens start with and how many tokens it takes up.
+fn date(tokens: &[Token], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let (skipped, tokens) = match tokens {
+        [Token::On, rest @ ..] => (1, rest),
+        _ => (0, tokens),
+    };
+
+    let (date, n) = match tokens {
+        [Token::Date(date), ..] => (*date, 1),
+        [Token::Weekday(day), ..] if skipped > 0 => (upcoming(*day, today), 1),
+        [Token::Next, Token::Weekday(day), ..] => (upcoming(*day, today) + Duration::weeks(1), 2),
+        [Token::Next, Token::Unit(unit), ..] => (unit.after(today, 1)?, 2),
+        [Token::In, Token::Number(count), Token::Unit(unit), ..] => (unit.after(today, *count)?, 3),
+        _ => return None,
+    };
+
+    Some((date, skipped + n))
+}
+
+/// The time the given tokens start with and how many tokens it takes up.
+fn time(tokens: &[Token]) -> Option<(NaiveTime, usize)> {
+    match tokens {
+        [Token::Time(time), ..] => Some((*time, 1)),
+        [Token::At, Token::Time(time), ..] | [Token::At, Token::Clock(time), ..] => Some((*time, 2)),
+        _ => None,
+    }
+}
+
+/// The first day on or after today that falls on the given day of the week.
+fn upcoming(day: Weekday, today: NaiveDate) -> NaiveDate {
+    let days = (7 + day.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(days.into())
+}
+
+/// Describe a date relative to today where that is shorter, like "tomorrow" or "Friday".
+fn describe_date(date: NaiveDate, today: NaiveDate) -> String {
+    match date.signed_duration_since(today).num_days() {
+        0 => "today".to_owned(),
+        1 => "tomorrow".to_owned(),
+        2..=6 => date.format("%A").to_string(),
+        _ if date.year() == today.year() => date.format("%a, %b %-d").to_string(),
+        _ => date.format("%a, %b %-d, %Y").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wednesday.
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 13).unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        // input, text, due date, due time, priority, tags
+        type Case = (&'static str, &'static str, Option<&'static str>, Option<&'static str>, Priority, &'static [&'static str]);
+        let cases: &[Case] = &[
+            ("Call vendor tomorrow 3pm !high #ops", "Call vendor", Some("2024-03-14"), Some("15:00"), Priority::High, &["ops"]),
+            ("buy milk", "buy milk", None, None, Priority::None, &[]),
+            ("  spaced   out   tomorrow ", "spaced out", Some("2024-03-14"), None, Priority::None, &[]),
+            ("", "", None, None, Priority::None, &[]),
+
+            // dates
+            ("buy milk today", "buy milk", Some("2024-03-13"), None, Priority::None, &[]),
+            ("Today buy milk", "buy milk", Some("2024-03-13"), None, Priority::None, &[]),
+            ("buy milk TOMORROW", "buy milk", Some("2024-03-14"), None, Priority::None, &[]),
+            ("pay rent on friday", "pay rent", Some("2024-03-15"), None, Priority::None, &[]),
+            ("pay rent by Wednesday", "pay rent", Some("2024-03-13"), None, Priority::None, &[]),
+            ("pay rent due tuesday", "pay rent", Some("2024-03-19"), None, Priority::None, &[]),
+            ("pay rent next friday", "pay rent", Some("2024-03-22"), None, Priority::None, &[]),
+            ("pay rent next wednesday", "pay rent", Some("2024-03-20"), None, Priority::None, &[]),
+            ("review next week", "review", Some("2024-03-20"), None, Priority::None, &[]),
+            ("review next month", "review", Some("2024-04-13"), None, Priority::None, &[]),
+            ("renew in 3 days", "renew", Some("2024-03-16"), None, Priority::None, &[]),
+            ("renew in 1 day", "renew", Some("2024-03-14"), None, Priority::None, &[]),
+            ("renew in 2 weeks", "renew", Some("2024-03-27"), None, Priority::None, &[]),
+            ("renew in 11 months", "renew", Some("2025-02-13"), None, Priority::None, &[]),
+            ("call mom on friday", "call mom", Some("2024-03-15"), None, Priority::None, &[]),
+            ("call mom on next friday", "call mom", Some("2024-03-22"), None, Priority::None, &[]),
+            ("due 2024-04-01 file taxes", "file taxes", Some("2024-04-01"), None, Priority::None, &[]),
+            ("file taxes by tomorrow", "file taxes", Some("2024-03-14"), None, Priority::None, &[]),
+            ("file taxes 2023-04-01", "file taxes", Some("2023-04-01"), None, Priority::None, &[]),
+
+            // times
+            ("standup at 9:30am", "standup", Some("2024-03-13"), Some("09:30"), Priority::None, &[]),
+            ("standup at 9:30", "standup", Some("2024-03-13"), Some("09:30"), Priority::None, &[]),
+            ("standup @ 9:30", "standup", Some("2024-03-13"), Some("09:30"), Priority::None, &[]),
+            ("standup @9:30", "standup", Some("2024-03-13"), Some("09:30"), Priority::None, &[]),
+            ("dinner 7PM", "dinner", Some("2024-03-13"), Some("19:00"), Priority::None, &[]),
+            ("dinner @7pm", "dinner", Some("2024-03-13"), Some("19:00"), Priority::None, &[]),
+            ("deploy at 15:45 on friday", "deploy", Some("2024-03-15"), Some("15:45"), Priority::None, &[]),
+            ("release 12am", "release", Some("2024-03-13"), Some("00:00"), Priority::None, &[]),
+            ("lunch 12pm", "lunch", Some("2024-03-13"), Some("12:00"), Priority::None, &[]),
+            ("lunch at noon", "lunch", Some("2024-03-13"), Some("12:00"), Priority::None, &[]),
+            ("read at 0:05", "read", Some("2024-03-13"), Some("00:05"), Priority::None, &[]),
+
+            // priorities
+            ("chores !low", "chores", None, None, Priority::Low, &[]),
+            ("!med chores", "chores", None, None, Priority::Medium, &[]),
+            ("chores !medium", "chores", None, None, Priority::Medium, &[]),
+            ("chores !HIGH", "chores", None, None, Priority::High, &[]),
+
+            // tags
+            ("#Ops call #work", "call", None, None, Priority::None, &["ops", "work"]),
+            ("call #ops #ops", "call", None, None, Priority::None, &["ops"]),
+
+            // only the first of each is used
+            ("call today or tomorrow", "call or tomorrow", Some("2024-03-13"), None, Priority::None, &[]),
+            ("x !high !low", "x !low", None, None, Priority::High, &[]),
+            ("x 3pm 4pm", "x 4pm", Some("2024-03-13"), Some("15:00"), Priority::None, &[]),
+            ("x at 3pm at 4pm", "x at 4pm", Some("2024-03-13"), Some("15:00"), Priority::None, &[]),
+
+            // things that look like the syntax but aren't
+            ("put it in the box", "put it in the box", None, None, Priority::None, &[]),
+            ("in 3 apples", "in 3 apples", None, None, Priority::None, &[]),
+            ("buy 3 apples", "buy 3 apples", None, None, Priority::None, &[]),
+            ("next steps", "next steps", None, None, Priority::None, &[]),
+            ("work on report", "work on report", None, None, Priority::None, &[]),
+            ("meet at home", "meet at home", None, None, Priority::None, &[]),
+            ("due diligence", "due diligence", None, None, Priority::None, &[]),
+            ("Friday's notes", "Friday's notes", None, None, Priority::None, &[]),
+            ("Take the SAT", "Take the SAT", None, None, Priority::None, &[]),
+            ("Enjoy the sun", "Enjoy the sun", None, None, Priority::None, &[]),
+            ("Get wed", "Get wed", None, None, Priority::None, &[]),
+            ("Buy a new mon itor", "Buy a new mon itor", None, None, Priority::None, &[]),
+            ("Watch Friday Night Lights", "Watch Friday Night Lights", None, None, Priority::None, &[]),
+            ("pay rent friday", "pay rent friday", None, None, Priority::None, &[]),
+            ("pay rent on fri", "pay rent on fri", None, None, Priority::None, &[]),
+            ("Read John 3:16", "Read John 3:16", None, None, Priority::None, &[]),
+            ("standup 9:30", "standup 9:30", None, None, Priority::None, &[]),
+            ("Watch High Noon", "Watch High Noon", None, None, Priority::None, &[]),
+            ("email me@home", "email me@home", None, None, Priority::None, &[]),
+            ("tomorrow, maybe", "tomorrow, maybe", None, None, Priority::None, &[]),
+            ("call 1500", "call 1500", None, None, Priority::None, &[]),
+            ("call 13pm", "call 13pm", None, None, Priority::None, &[]),
+            ("call 0am", "call 0am", None, None, Priority::None, &[]),
+            ("call 25:00", "call 25:00", None, None, Priority::None, &[]),
+            ("call 9:5", "call 9:5", None, None, Priority::None, &[]),
+            ("call 9:60", "call 9:60", None, None, Priority::None, &[]),
+            ("call +3pm", "call +3pm", None, None, Priority::None, &[]),
+            ("call pm", "call pm", None, None, Priority::None, &[]),
+            ("submit 2024-02-30", "submit 2024-02-30", None, None, Priority::None, &[]),
+            ("x !urgent", "x !urgent", None, None, Priority::None, &[]),
+            ("wow!", "wow!", None, None, Priority::None, &[]),
+            ("x # #Not_a-Tag!", "x # #Not_a-Tag!", None, None, Priority::None, &[]),
+
+            // without any other text, the words are the text
+            ("today", "today", None, None, Priority::None, &[]),
+            ("Next Friday", "Next Friday", None, None, Priority::None, &[]),
+            ("tomorrow !high #x", "tomorrow !high", None, None, Priority::None, &["x"]),
+            // which can leave nothing, for the model to refuse
+            ("#x", "", None, None, Priority::None, &["x"]),
+        ];
+
+        for &(input, text, due, due_time, priority, tags) in cases {
+            let expected = QuickAdd {
+                text: text.to_owned(),
+                tags: tags.iter().map(|&tag| tag.to_owned()).collect(),
+                due: due.map(date),
+                due_time: due_time.map(time),
+                priority,
+            };
+            assert_eq!(QuickAdd::parse(input, today()), expected, "parsing {:?}", input);
+        }
+    }
+
+    #[test]
+    fn relative_to_today() {
+        // input, today, due date
+        let cases = [
+            ("x on friday", "2024-03-15", "2024-03-15"),
+            ("x on friday", "2024-03-16", "2024-03-22"),
+            ("x next friday", "2024-03-16", "2024-03-29"),
+            ("x on sunday", "2024-03-11", "2024-03-17"),
+            ("x by monday", "2024-03-17", "2024-03-18"),
+            ("x tomorrow", "2024-02-28", "2024-02-29"),
+            ("x tomorrow", "2024-12-31", "2025-01-01"),
+            ("x next month", "2024-01-31", "2024-02-29"),
+            ("x in 1 week", "2024-12-28", "2025-01-04"),
+        ];
+
+        for &(input, today, due) in &cases {
+            assert_eq!(QuickAdd::parse(input, date(today)).due, Some(date(due)), "{:?} on {}", input, today);
+        }
+    }
+
+    #[test]
+    fn preview() {
+        let cases: &[(&str, &[&str])] = &[
+            ("Call vendor tomorrow 3pm !high #ops", &["Due tomorrow at 3:00 PM", "Priority: high", "#ops"]),
+            ("x today", &["Due today"]),
+            ("x on friday", &["Due Friday"]),
+            ("x next tuesday", &["Due Tue, Mar 26"]),
+            ("x 2024-03-01", &["Due Fri, Mar 1"]),
+            ("x 2025-01-02", &["Due Thu, Jan 2, 2025"]),
+            ("x at 9:30am", &["Due today at 9:30 AM"]),
+            ("x !low #b #a", &["Priority: low", "#a", "#b"]),
+            ("#home", &["#home"]),
+            ("buy milk", &[]),
+            ("today", &[]),
+        ];
+
+        for &(input, expected) in cases {
+            assert_eq!(QuickAdd::parse(input, today()).preview(today()), expected, "previewing {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Rules for items that come back after being completed.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize,Deserialize};
+use chrono::{Datelike,Duration,Months,NaiveDate,Weekday};
+
+/// How often an item recurs.
+///
+/// Rules are stored using their names: `daily`, `weekdays`, `weekly:mon`, `monthly:31` and
+/// `every:3`.
+#[derive(Clone,Copy,PartialEq,Eq,Debug,Serialize,Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Recurrence {
+    Daily,
+    /// Monday through Friday.
+    Weekdays,
+    Weekly(Weekday),
+    /// The given day every month, or the last day of shorter months.
+    Monthly(u32),
+    /// Every given number of days, at least one.
+    EveryDays(u32),
+}
+
+/// Days of the week with the names used for them in rules and descriptions.
+const WEEKDAYS: [(Weekday, &str, &str); 7] = [
+    (Weekday::Mon, "mon", "Monday"),
+    (Weekday::Tue, "tue", "Tuesday"),
+    (Weekday::Wed, "wed", "Wednesday"),
+    (Weekday::Thu, "thu", "Thursday"),
+    (Weekday::Fri, "fri", "Friday"),
+    (Weekday::Sat, "sat", "Saturday"),
+    (Weekday::Sun, "sun", "Sunday"),
+];
+
+fn weekday_names(day: Weekday) -> (&'static str, &'static str) {
+    let &(_, short, long) = &WEEKDAYS[day.num_days_from_monday() as usize];
+    (short, long)
+}
+
+impl Recurrence {
+    /// The rules offered when picking one, weekly rules for each day of the week and a monthly
+    /// one on the day of the month of the given date.
+    pub fn common(on: NaiveDate) -> impl Iterator<Item = Recurrence> {
+        std::iter::once(Recurrence::Daily)
+            .chain(std::iter::once(Recurrence::Weekdays))
+            .chain(WEEKDAYS.iter().map(|&(day, _, _)| Recurrence::Weekly(day)))
+            .chain(std::iter::once(Recurrence::Monthly(on.day())))
+    }
+
+    /// The first day after the given one this rule falls on.
+    pub fn next(self, after: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => after + Duration::days(1),
+            Recurrence::Weekdays => {
+                let mut next = after + Duration::days(1);
+                while matches!(next.weekday(), Weekday::Sat | Weekday::Sun) {
+                    next += Duration::days(1);
+                }
+                next
+            }
+            Recurrence::Weekly(day) => {
+                let days = (7 + day.num_days_from_monday() - after.weekday().num_days_from_monday()) % 7;
+                after + Duration::days(if days == 0 { 7 } else { days.into() })
+            }
+            // counting from the day of the rule, not the date after, so a shorter month in between
+            // doesn't move later ones
+            Recurrence::Monthly(day) => {
+                let this_month = day_of_month(after, day);
+                if this_month > after {
+                    this_month
+                }
+                else {
+                    day_of_month(after.checked_add_months(Months::new(1)).unwrap_or(after), day)
+                }
+            }
+            Recurrence::EveryDays(n) => after + Duration::days(n.max(1).into()),
+        }
+    }
+
+    /// A description of the rule for people.
+    pub fn describe(self) -> String {
+        match self {
+            Recurrence::Daily => "Every day".to_owned(),
+            Recurrence::Weekdays => "Every weekday".to_owned(),
+            Recurrence::Weekly(day) => format!("Every {}", weekday_names(day).1),
+            Recurrence::Monthly(day) => format!("Every month on the {}", ordinal(day)),
+            Recurrence::EveryDays(1) => "Every day".to_owned(),
+            Recurrence::EveryDays(n) => format!("Every {} days", n),
+        }
+    }
+}
+
+/// The given day in the month of a date, or the last day of the month if it is shorter.
+fn day_of_month(date: NaiveDate, day: u32) -> NaiveDate {
+    let first = date - Duration::days(date.day0().into());
+    let last = first.checked_add_months(Months::new(1)).map_or(date, |next| next - Duration::days(1));
+    first + Duration::days((day.clamp(1, last.day()) - 1).into())
+}
+
+/// A day of the month for people, like "1st" or "22nd".
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekdays => write!(f, "weekdays"),
+            Recurrence::Weekly(day) => write!(f, "weekly:{}", weekday_names(*day).0),
+            Recurrence::Monthly(day) => write!(f, "monthly:{}", day),
+            Recurrence::EveryDays(n) => write!(f, "every:{}", n),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid recurrence '{}'", s);
+
+        match s.split_once(':') {
+            None => match s {
+                "daily" => Ok(Recurrence::Daily),
+                "weekdays" => Ok(Recurrence::Weekdays),
+                _ => Err(invalid()),
+            },
+            Some(("monthly", day)) => match day.parse() {
+                Ok(day) if (1..=31).contains(&day) => Ok(Recurrence::Monthly(day)),
+                _ => Err(invalid()),
+            },
+            Some(("weekly", name)) => WEEKDAYS.iter()
+                .find(|&&(_, short, _)| short == name)
+                .map(|&(day, _, _)| Recurrence::Weekly(day))
+                .ok_or_else(invalid),
+            Some(("every", n)) => match n.parse() {
+                Ok(n) if n > 0 => Ok(Recurrence::EveryDays(n)),
+                _ => Err(invalid()),
+            },
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn next() {
+        // 2024-03-01 is a friday
+        let cases = [
+            (Recurrence::Daily, "2024-03-01", "2024-03-02"),
+            (Recurrence::Daily, "2024-12-31", "2025-01-01"),
+            (Recurrence::Weekdays, "2024-03-01", "2024-03-04"),
+            (Recurrence::Weekdays, "2024-03-02", "2024-03-04"),
+            (Recurrence::Weekdays, "2024-03-04", "2024-03-05"),
+            (Recurrence::Weekly(Weekday::Fri), "2024-03-01", "2024-03-08"),
+            (Recurrence::Weekly(Weekday::Mon), "2024-03-01", "2024-03-04"),
+            (Recurrence::Weekly(Weekday::Thu), "2024-03-01", "2024-03-07"),
+            (Recurrence::Monthly(1), "2024-03-01", "2024-04-01"),
+            (Recurrence::Monthly(31), "2024-01-31", "2024-02-29"),
+            (Recurrence::Monthly(31), "2024-02-29", "2024-03-31"),
+            (Recurrence::Monthly(31), "2024-03-31", "2024-04-30"),
+            (Recurrence::Monthly(30), "2024-04-30", "2024-05-30"),
+            (Recurrence::Monthly(15), "2024-12-15", "2025-01-15"),
+            (Recurrence::Monthly(15), "2024-03-01", "2024-03-15"),
+            (Recurrence::Monthly(15), "2024-03-20", "2024-04-15"),
+            (Recurrence::EveryDays(3), "2024-02-28", "2024-03-02"),
+            (Recurrence::EveryDays(0), "2024-03-01", "2024-03-02"),
+        ];
+
+        for &(recurrence, after, expected) in &cases {
+            assert_eq!(recurrence.next(date(after)), date(expected), "{} after {}", recurrence, after);
+        }
+    }
+
+    #[test]
+    fn names() {
+        for recurrence in Recurrence::common(date("2024-03-31")).chain(std::iter::once(Recurrence::EveryDays(10))) {
+            assert_eq!(recurrence.to_string().parse(), Ok(recurrence));
+        }
+
+        assert_eq!("weekly:fri".parse(), Ok(Recurrence::Weekly(Weekday::Fri)));
+        assert_eq!("monthly:31".parse(), Ok(Recurrence::Monthly(31)));
+        assert!("monthly:0".parse::<Recurrence>().is_err());
+        assert!("monthly:32".parse::<Recurrence>().is_err());
+        assert!("monthly".parse::<Recurrence>().is_err());
+        assert_eq!("every:3".parse(), Ok(Recurrence::EveryDays(3)));
+        assert!("every:0".parse::<Recurrence>().is_err());
+        assert!("weekly:friday".parse::<Recurrence>().is_err());
+        assert!("hourly".parse::<Recurrence>().is_err());
+
+        assert_eq!(serde_json::to_string(&Recurrence::Weekly(Weekday::Mon)).unwrap(), "\"weekly:mon\"");
+        assert!(serde_json::from_str::<Recurrence>("\"yearly\"").is_err());
+    }
+
+    #[test]
+    fn describe() {
+        assert_eq!(Recurrence::Monthly(1).describe(), "Every month on the 1st");
+        assert_eq!(Recurrence::Monthly(12).describe(), "Every month on the 12th");
+        assert_eq!(Recurrence::Monthly(22).describe(), "Every month on the 22nd");
+        assert_eq!(Recurrence::Monthly(23).describe(), "Every month on the 23rd");
+        assert_eq!(Recurrence::Weekly(Weekday::Fri).describe(), "Every Friday");
+    }
+}
@@ -0,0 +1,346 @@
+//! Mapping between urls and what the app shows.
+//!
+//! Routes live in the url fragment and look like
+//! `#/list/<name>/<view>/tag/<tag>/item/<id>/edit/<filter>?sort=<sort>&q=<search>`, where every
+//! part is optional. Unknown query parameters are ignored, anything else that isn't understood is an
+//! error.
+
+use std::fmt;
+
+use super::{Filter,SortMode,ItemId,View};
+use super::item::is_tag;
+
+/// The part of the app's state reflected in the url.
+#[derive(PartialEq,Clone,Debug,Default)]
+pub struct AppRoute {
+    /// The list to show, `None` for the first one.
+    pub list: Option<String>,
+    pub view: View,
+    pub filter: Filter,
+    /// An item to bring into view.
+    pub item: Option<ItemId>,
+    /// Start editing the item.
+    pub edit: bool,
+    pub sort: SortMode,
+    /// Only show items with this tag.
+    pub tag: Option<String>,
+    /// The search query, empty if not searching.
+    pub search: String,
+}
+
+#[derive(PartialEq,Debug)]
+pub enum Error {
+    /// A path segment that isn't a filter or the start of a known section.
+    UnknownSegment(String),
+    /// A section like `list` without the value that should follow it.
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    /// A section or filter given more than once.
+    Duplicate(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownSegment(segment) => write!(f, "unknown path segment '{}'", segment),
+            Error::MissingValue(name) => write!(f, "missing value for '{}'", name),
+            Error::InvalidValue(name, value) => write!(f, "invalid {} '{}'", name, value),
+            Error::Duplicate(name) => write!(f, "more than one {}", name),
+        }
+    }
+}
+
+impl AppRoute {
+    /// Parse a url, or just the fragment of one. Urls without a fragment get the default route.
+    pub fn parse(url: &str) -> Result<Self, Error> {
+        let fragment = match url.find('#') {
+            Some(i) => &url[i + 1..],
+            None => "",
+        };
+
+        let (path, query) = fragment.split_once('?').unwrap_or((fragment, ""));
+
+        let mut route = AppRoute::default();
+        let mut filter = None;
+
+        // ignore empty segments so trailing (or doubled) slashes don't matter
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty()).peekable();
+        while let Some(segment) = segments.next() {
+            let mut value = |name: &'static str| {
+                let value = segments.next().ok_or(Error::MissingValue(name))?;
+                decode(value).ok_or_else(|| Error::InvalidValue(name, value.to_owned()))
+            };
+
+            match segment {
+                "list" => {
+                    let name = value("list")?;
+                    if route.list.replace(name).is_some() {
+                        return Err(Error::Duplicate("list"));
+                    }
+                }
+                "tag" => {
+                    let tag = value("tag")?;
+                    if !is_tag(&tag) {
+                        return Err(Error::InvalidValue("tag", tag));
+                    }
+                    if route.tag.replace(tag).is_some() {
+                        return Err(Error::Duplicate("tag"));
+                    }
+                }
+                "archive" | "trash" => {
+                    if route.view != View::Items {
+                        return Err(Error::Duplicate("view"));
+                    }
+                    route.view = if segment == "archive" { View::Archive } else { View::Trash };
+                }
+                "item" => {
+                    let id = value("item")?;
+                    let id = id.parse().map_err(|_| Error::InvalidValue("item", id))?;
+                    if route.item.replace(id).is_some() {
+                        return Err(Error::Duplicate("item"));
+                    }
+                    // only valid directly after the item it applies to
+                    route.edit = segments.next_if_eq(&"edit").is_some();
+                }
+                _ => match Filter::from_name(segment) {
+                    Some(f) if filter.replace(f).is_some() => return Err(Error::Duplicate("filter")),
+                    Some(_) => {}
+                    None => return Err(Error::UnknownSegment(segment.to_owned())),
+                },
+            }
+        }
+        route.filter = filter.unwrap_or_default();
+
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let decoded = || decode(&value.replace('+', " "));
+            match name {
+                "sort" => {
+                    route.sort = decoded()
+                        .and_then(|sort| SortMode::from_name(&sort))
+                        .ok_or_else(|| Error::InvalidValue("sort", value.to_owned()))?;
+                }
+                "q" => {
+                    route.search = decoded()
+                        .ok_or_else(|| Error::InvalidValue("search", value.to_owned()))?;
+                }
+                // leave room for parameters used by other versions of the app
+                _ => {}
+            }
+        }
+
+        Ok(route)
+    }
+
+    /// Build the url fragment for this route.
+    pub fn to_url(&self) -> String {
+        let mut segments = vec![];
+        if let Some(ref list) = self.list {
+            segments.push(format!("list/{}", encode(list)));
+        }
+        match self.view {
+            View::Items => {}
+            View::Archive => segments.push("archive".to_owned()),
+            View::Trash => segments.push("trash".to_owned()),
+        }
+        if let Some(ref tag) = self.tag {
+            segments.push(format!("tag/{}", tag));
+        }
+        if let Some(item) = self.item {
+            segments.push(format!("item/{}", item));
+            if self.edit {
+                segments.push("edit".to_owned());
+            }
+        }
+        if self.filter != Filter::All {
+            segments.push(self.filter.name().to_owned());
+        }
+
+        let mut params = vec![];
+        if self.sort != SortMode::Insertion {
+            params.push(format!("sort={}", self.sort.name()));
+        }
+        if !self.search.is_empty() {
+            params.push(format!("q={}", encode(&self.search)));
+        }
+
+        let path = format!("#/{}", segments.join("/"));
+        if params.is_empty() {
+            path
+        }
+        else {
+            format!("{}?{}", path, params.join("&"))
+        }
+    }
+}
+
+/// Percent encode everything but unreserved characters.
+fn encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decode a percent encoded string, returning `None` if it's malformed.
+fn decode(s: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        }
+        else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> AppRoute {
+        AppRoute::parse(url).unwrap()
+    }
+
+    #[test]
+    fn parse_paths() {
+        assert_eq!(parse("http://localhost:8080"), AppRoute::default());
+        assert_eq!(parse("http://localhost:8080/#"), AppRoute::default());
+        assert_eq!(parse("#/all"), AppRoute::default());
+        assert_eq!(parse("#/active/").filter, Filter::Active);
+        assert_eq!(parse("#//completed//").filter, Filter::Completed);
+        assert_eq!(parse("#/item/12/due"), AppRoute {
+            filter: Filter::Due,
+            item: Some(12),
+            .. AppRoute::default()
+        });
+        assert_eq!(parse("#/list/home/archive"), AppRoute {
+            list: Some("home".to_owned()),
+            view: View::Archive,
+            .. AppRoute::default()
+        });
+        assert_eq!(parse("#/trash/"), AppRoute {
+            view: View::Trash,
+            .. AppRoute::default()
+        });
+        assert_eq!(parse("#/item/12/edit/"), AppRoute {
+            item: Some(12),
+            edit: true,
+            .. AppRoute::default()
+        });
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(AppRoute::parse("#/bogus"), Err(Error::UnknownSegment("bogus".to_owned())));
+        assert_eq!(AppRoute::parse("#/active/completed"), Err(Error::Duplicate("filter")));
+        assert_eq!(AppRoute::parse("#/archive/archive"), Err(Error::Duplicate("view")));
+        assert_eq!(AppRoute::parse("#/archive/trash"), Err(Error::Duplicate("view")));
+        assert_eq!(AppRoute::parse("#/list"), Err(Error::MissingValue("list")));
+        assert_eq!(AppRoute::parse("#/list/a/list/b"), Err(Error::Duplicate("list")));
+        assert_eq!(AppRoute::parse("#/list/%zz"), Err(Error::InvalidValue("list", "%zz".to_owned())));
+        assert_eq!(AppRoute::parse("#/tag/Not%20A%20Tag"), Err(Error::InvalidValue("tag", "Not A Tag".to_owned())));
+        assert_eq!(AppRoute::parse("#/item/one"), Err(Error::InvalidValue("item", "one".to_owned())));
+        assert_eq!(AppRoute::parse("#/edit/item/1"), Err(Error::UnknownSegment("edit".to_owned())));
+        assert_eq!(AppRoute::parse("#/item/1/active/edit"), Err(Error::UnknownSegment("edit".to_owned())));
+        assert_eq!(AppRoute::parse("#/?sort=bogus"), Err(Error::InvalidValue("sort", "bogus".to_owned())));
+        assert_eq!(AppRoute::parse("#/?q=%zz"), Err(Error::InvalidValue("search", "%zz".to_owned())));
+    }
+
+    #[test]
+    fn parse_sort() {
+        assert_eq!(parse("http://localhost:8080/#/active?sort=priority"), AppRoute {
+            filter: Filter::Active,
+            sort: SortMode::Priority,
+            .. AppRoute::default()
+        });
+        assert_eq!(parse("#/?sort=alphabetical").sort, SortMode::Alphabetical);
+
+        // unknown parameters are ignored
+        assert_eq!(parse("#/active?x=1&sort=priority&y").sort, SortMode::Priority);
+    }
+
+    #[test]
+    fn parse_tag() {
+        let route = parse("#/tag/work/active?sort=priority");
+        assert_eq!(route.tag.as_deref(), Some("work"));
+        assert_eq!(route.filter, Filter::Active);
+        assert_eq!(route.sort, SortMode::Priority);
+
+        assert_eq!(parse("#/tag/work").tag.as_deref(), Some("work"));
+        assert_eq!(parse("#/tag/work/").filter, Filter::All);
+    }
+
+    #[test]
+    fn parse_list() {
+        let route = parse("#/list/sprint%2012/tag/work/active");
+        assert_eq!(route.list.as_deref(), Some("sprint 12"));
+        assert_eq!(route.tag.as_deref(), Some("work"));
+        assert_eq!(route.filter, Filter::Active);
+
+        assert_eq!(parse("#/list/home").list.as_deref(), Some("home"));
+    }
+
+    #[test]
+    fn parse_search() {
+        let route = parse("#/active?q=buy%20milk&sort=alphabetical");
+        assert_eq!(route.search, "buy milk");
+        assert_eq!(route.sort, SortMode::Alphabetical);
+        assert_eq!(route.filter, Filter::Active);
+
+        assert_eq!(parse("#/?q=buy+milk").search, "buy milk");
+        assert_eq!(AppRoute { search: "50% off & more".to_owned(), .. AppRoute::default() }.to_url(), "#/?q=50%25%20off%20%26%20more");
+    }
+
+    #[test]
+    fn to_url() {
+        assert_eq!(AppRoute::default().to_url(), "#/");
+        assert_eq!(AppRoute {
+            list: Some("home".to_owned()),
+            view: View::Archive,
+            filter: Filter::Active,
+            item: Some(3),
+            edit: true,
+            sort: SortMode::Priority,
+            tag: Some("work".to_owned()),
+            search: "milk".to_owned(),
+        }.to_url(), "#/list/home/archive/tag/work/item/3/edit/active?sort=priority&q=milk");
+    }
+
+    #[test]
+    fn round_trip() {
+        let items = [(None, false), (Some(7), false), (Some(7), true)];
+
+        for &filter in &Filter::ALL {
+            for &sort in &SortMode::ALL {
+                for tag in &[None, Some("work".to_owned())] {
+                    for list in &[None, Some("home".to_owned()), Some("Q3 / 100% done".to_owned())] {
+                        for search in &["", "milk", "a+b & c=d?"] {
+                            for &view in &[View::Items, View::Archive, View::Trash] {
+                                for &(item, edit) in &items {
+                                    let route = AppRoute {
+                                        list: list.clone(),
+                                        view,
+                                        filter,
+                                        item,
+                                        edit,
+                                        sort,
+                                        tag: tag.clone(),
+                                        search: search.to_string(),
+                                    };
+                                    assert_eq!(parse(&route.to_url()), route);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! The format todos are persisted in.
+//!
+//! Data is stored as a json envelope carrying a schema version alongside the items:
+//!
+//! ```json
+//! {"version": 3, "items": [{"id": 1, "title": "do stuff", "completed": false}]}
+//! ```
+//!
+//! Older versions are upgraded by running them through the chain of `MIGRATIONS` before being
+//! deserialized.
+
+use std::fmt;
+use serde::{Serialize,Deserialize};
+use serde_json::{json,Value};
+
+use super::item::{Item,assign_missing_ids};
+
+/// The current schema version.
+pub const VERSION: u64 = 3;
+
+/// Converts data from one schema version to the next.
+type Migration = fn(Value) -> Result<Value, Error>;
+
+/// Migrations between schema versions. The migration at index `i` converts data from version
+/// `i + 1` to version `i + 2`.
+const MIGRATIONS: &[Migration] = &[
+    v1_to_v2,
+    v2_to_v3,
+];
+
+#[derive(Debug)]
+pub enum Error {
+    /// The data was not valid json or didn't match the expected shape.
+    Parse(serde_json::Error),
+    /// The data was written by a version we don't know about.
+    UnknownVersion(u64),
+    /// The data claimed to be a known version but couldn't be migrated.
+    Migration(u64, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::UnknownVersion(v) => write!(f, "unknown schema version {}", v),
+            Error::Migration(v, e) => write!(f, "error migrating from schema version {}: {}", v, e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e)
+    }
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    version: u64,
+    items: &'a [Item],
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    items: Vec<Item>,
+}
+
+/// Serialize items in the current schema.
+pub fn encode(items: &[Item]) -> String {
+    serde_json::to_string(&EnvelopeRef {
+        version: VERSION,
+        items,
+    }).unwrap()
+}
+
+/// Deserialize items stored in the current or any older schema.
+pub fn decode(data: &str) -> Result<Vec<Item>, Error> {
+    let mut value: Value = serde_json::from_str(data)?;
+
+    loop {
+        let version = version_of(&value);
+        if version == VERSION {
+            break;
+        }
+
+        let migration = version.checked_sub(1)
+            .and_then(|i| MIGRATIONS.get(i as usize))
+            .ok_or(Error::UnknownVersion(version))?;
+
+        value = migration(value)?;
+    }
+
+    let mut envelope: Envelope = serde_json::from_value(value)?;
+
+    // items stored by older versions don't have ids
+    assign_missing_ids(&mut envelope.items);
+
+    Ok(envelope.items)
+}
+
+/// Determine the schema version of some stored data.
+fn version_of(value: &Value) -> u64 {
+    match value {
+        // version 1 was a bare array of items
+        Value::Array(_) => 1,
+        Value::Object(map) => map.get("version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Wrap the bare item array in an envelope.
+fn v1_to_v2(value: Value) -> Result<Value, Error> {
+    match value {
+        Value::Array(_) => Ok(json!({
+            "version": 2,
+            "items": value,
+        })),
+        _ => Err(Error::Migration(1, "expected an array of items".to_owned())),
+    }
+}
+
+/// Give monthly rules the day of the month they fall on, taken from the due date of their item.
+fn v2_to_v3(mut value: Value) -> Result<Value, Error> {
+    fn anchor_monthly(item: &mut Value) {
+        let day = item.get("due")
+            .and_then(Value::as_str)
+            .and_then(|due| due.get(8..10))
+            .and_then(|day| day.parse::<u32>().ok())
+            .unwrap_or(1);
+
+        if item.get("recurrence").and_then(Value::as_str) == Some("monthly") {
+            item["recurrence"] = json!(format!("monthly:{}", day));
+        }
+        if let Some(Value::Array(children)) = item.get_mut("children") {
+            children.iter_mut().for_each(anchor_monthly);
+        }
+    }
+
+    match value.get_mut("items") {
+        Some(Value::Array(items)) => items.iter_mut().for_each(anchor_monthly),
+        _ => return Err(Error::Migration(2, "expected an array of items".to_owned())),
+    }
+    value["version"] = json!(3);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::todo::Recurrence;
+
+    #[test]
+    fn round_trip() {
+        let items = vec![
+            Item {
+                id: 1,
+                text: "one".to_owned(),
+                .. Item::default()
+            },
+            Item {
+                id: 2,
+                text: "two".to_owned(),
+                is_complete: true,
+                .. Item::default()
+            },
+        ];
+
+        let decoded = decode(&encode(&items)).unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].text, "one");
+        assert_eq!(decoded[1].id, 2);
+        assert!(decoded[1].is_complete);
+    }
+
+    #[test]
+    fn migrate_bare_array() {
+        let items = decode(r#"[{"title":"one","completed":true}]"#).unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].text, "one");
+        assert!(items[0].is_complete);
+        assert!(items[0].id != 0);
+        assert_eq!(items[0].due, None);
+    }
+
+    #[test]
+    fn due_dates() {
+        let items = decode(r#"{"version":2,"items":[{"id":1,"title":"one","completed":false,"due":"2020-03-15"}]}"#).unwrap();
+        assert_eq!(items[0].due, chrono::NaiveDate::from_ymd_opt(2020, 3, 15));
+
+        // items without a due date are stored as before
+        let items = vec![Item::default()];
+        assert!(!encode(&items).contains("due"));
+    }
+
+    #[test]
+    fn migrate_monthly() {
+        let items = decode(r#"{"version":2,"items":[
+            {"id":1,"title":"rent","completed":false,"due":"2024-01-31","recurrence":"monthly","children":[
+                {"id":2,"title":"transfer","completed":false,"recurrence":"monthly"}
+            ]},
+            {"id":3,"title":"run","completed":false,"due":"2024-01-31","recurrence":"daily"}
+        ]}"#).unwrap();
+
+        assert_eq!(items[0].recurrence, Some(Recurrence::Monthly(31)));
+        assert_eq!(items[0].children[0].recurrence, Some(Recurrence::Monthly(1)));
+        assert_eq!(items[1].recurrence, Some(Recurrence::Daily));
+    }
+
+    #[test]
+    fn reject_unknown() {
+        match decode(r#"{"version":99,"items":[]}"#) {
+            Err(Error::UnknownVersion(99)) => {}
+            _ => panic!("expected unknown version error"),
+        }
+
+        match decode(r#"{"items":[]}"#) {
+            Err(Error::UnknownVersion(0)) => {}
+            _ => panic!("expected unknown version error"),
+        }
+
+        match decode("not json") {
+            Err(Error::Parse(_)) => {}
+            _ => panic!("expected parse error"),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Matching item text against a search query.
+
+use std::ops::Range;
+
+/// Find where a query matches some text, ignoring case.
+///
+/// Returns the byte ranges of the matched parts of the text, or `None` if the query doesn't
+/// match. An empty query matches everything. With `fuzzy` set, the characters of the query only
+/// need to appear in order (so "bmk" matches "buy milk") if the query isn't a substring.
+pub fn find(text: &str, query: &str, fuzzy: bool) -> Option<Vec<Range<usize>>> {
+    let query: Vec<char> = query.trim().chars().collect();
+    if query.is_empty() {
+        return Some(vec![]);
+    }
+
+    let len = text.len();
+    let text: Vec<(usize, char)> = text.char_indices().collect();
+    // the byte offset of the end of a range ending before the i'th char
+    let end = |i: usize| text.get(i).map_or(len, |&(i, _)| i);
+
+    // substring match
+    let found = (0..text.len())
+        .find(|&start| {
+            text.len() - start >= query.len()
+                && query.iter().zip(&text[start..]).all(|(&q, &(_, c))| eq(q, c))
+        });
+    if let Some(start) = found {
+        return Some(std::iter::once(text[start].0..end(start + query.len())).collect());
+    }
+
+    if !fuzzy {
+        return None;
+    }
+
+    // fuzzy match, merging runs of adjacent characters into one range
+    let mut ranges: Vec<Range<usize>> = vec![];
+    let mut chars = text.iter().enumerate();
+    for &q in query.iter().filter(|c| !c.is_whitespace()) {
+        let (i, &(start, _)) = chars.find(|&(_, &(_, c))| eq(q, c))?;
+        match ranges.last_mut() {
+            Some(last) if last.end == start => last.end = end(i + 1),
+            _ => ranges.push(start..end(i + 1)),
+        }
+    }
+
+    Some(ranges)
+}
+
+fn eq(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(text: &str, query: &str, fuzzy: bool) -> Option<Vec<(usize, usize)>> {
+        find(text, query, fuzzy).map(|ranges| ranges.into_iter().map(|r| (r.start, r.end)).collect())
+    }
+
+    #[test]
+    fn substring() {
+        assert_eq!(spans("Buy milk", "MILK", false), Some(vec![(4, 8)]));
+        assert_eq!(spans("Buy milk", "  buy ", false), Some(vec![(0, 3)]));
+        assert_eq!(spans("Buy milk", "", false), Some(vec![]));
+        assert_eq!(spans("Buy milk", "bread", false), None);
+        assert_eq!(spans("Café au lait", "É", false), Some(vec![(3, 5)]));
+        assert_eq!(spans("mi", "milk", false), None);
+    }
+
+    #[test]
+    fn fuzzy() {
+        assert_eq!(spans("Buy milk", "bmk", false), None);
+        assert_eq!(spans("Buy milk", "bmk", true), Some(vec![(0, 1), (4, 5), (7, 8)]));
+        assert_eq!(spans("Buy milk", "bu ilk", true), Some(vec![(0, 2), (5, 8)]));
+        assert_eq!(spans("Buy milk", "milk", true), Some(vec![(4, 8)]));
+        assert_eq!(spans("Buy milk", "kb", true), None);
+    }
+}
@@ -0,0 +1,46 @@
+//! Preferences shared by all lists.
+
+use log::error;
+use serde::{Serialize,Deserialize};
+
+use super::storage::TodoStore;
+
+/// The key the settings are stored under.
+pub const SETTINGS_KEY: &str = "todo-euca-settings";
+
+#[derive(Clone,PartialEq,Debug,Serialize,Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Whether completing an item also completes its subtasks.
+    pub complete_children: bool,
+    /// Whether searches also match items containing the characters of the query in order.
+    pub fuzzy_search: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            complete_children: true,
+            fuzzy_search: false,
+        }
+    }
+}
+
+pub fn encode(settings: &Settings) -> String {
+    serde_json::to_string(settings).expect("error serializing settings")
+}
+
+pub fn decode(data: &str) -> Result<Settings, serde_json::Error> {
+    serde_json::from_str(data)
+}
+
+/// Read the settings from storage, falling back to the defaults.
+pub fn read_settings(store: &dyn TodoStore) -> Settings {
+    match store.load(SETTINGS_KEY) {
+        Some(data) => decode(&data).unwrap_or_else(|e| {
+            error!("error reading settings: {}", e);
+            Settings::default()
+        }),
+        None => Settings::default(),
+    }
+}
@@ -0,0 +1,36 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A backend for persisting serialized todo data.
+///
+/// Data is stored as strings under string keys, mirroring the `Storage` web api.
+pub trait TodoStore {
+    /// Load the data stored under the given key, if any.
+    fn load(&self, key: &str) -> Option<String>;
+
+    /// Store data under the given key, replacing anything already there.
+    fn save(&self, key: &str, data: &str);
+
+    /// Remove any data stored under the given key.
+    fn clear(&self, key: &str);
+}
+
+/// A store that keeps everything in memory, useful for testing.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: RefCell<HashMap<String, String>>,
+}
+
+impl TodoStore for MemoryStore {
+    fn load(&self, key: &str) -> Option<String> {
+        self.data.borrow().get(key).cloned()
+    }
+
+    fn save(&self, key: &str, data: &str) {
+        self.data.borrow_mut().insert(key.to_owned(), data.to_owned());
+    }
+
+    fn clear(&self, key: &str) {
+        self.data.borrow_mut().remove(key);
+    }
+}
@@ -0,0 +1,600 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use cfg_if::cfg_if;
+use log::{debug,info,warn,error};
+use euca::app::*;
+use euca::route::Route;
+use std::rc::Rc;
+
+mod render;
+
+use crate::todo::{self,Todo,Message,Command,AppRoute,Filter};
+use crate::todo::lists::{self,LISTS_KEY};
+use crate::todo::settings::{self,SETTINGS_KEY};
+use crate::todo::storage::TodoStore;
+use crate::todo::schema;
+use crate::todo::export::Format;
+
+cfg_if! {
+    if #[cfg(feature = "console_error_panic_hook")] {
+        #[inline]
+        fn set_panic_hook() {
+            console_error_panic_hook::set_once();
+            debug!("panic hook set");
+        }
+    }
+    else {
+        fn set_panic_hook() {}
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "console_log")] {
+        #[inline]
+        fn init_log() {
+            console_log::init_with_level(log::Level::Trace)
+                .expect("error initializing log");
+            debug!("log initialized");
+        }
+    }
+    else {
+        fn init_log() {}
+    }
+}
+
+const TITLE: &str = "Euca • TodoMVC";
+
+/// Completed items older than this many days are archived on startup, `None` keeps them in the
+/// list until they are cleared.
+const AUTO_ARCHIVE_DAYS: Option<u32> = Some(30);
+
+/// Items in the trash longer than this many days are deleted for good on startup, `None` keeps
+/// them until the trash is emptied.
+const TRASH_DAYS: Option<u32> = Some(30);
+
+impl Update<Message, Command> for Todo {
+    fn update(&mut self, msg: Message, cmds: &mut Commands<Command>) {
+        let mut todo_cmds = todo::Commands::default();
+        Todo::update(self, msg, &mut todo_cmds);
+        todo_cmds.post_render.push(Command::SetTitle(self.title(TITLE)));
+
+        cmds.immediate.extend(todo_cmds.immediate);
+        cmds.post_render.extend(todo_cmds.post_render);
+    }
+}
+
+/// A store backed by `window.localStorage`.
+#[derive(Default)]
+pub struct LocalStorage {}
+
+impl LocalStorage {
+    fn storage(&self) -> web_sys::Storage {
+        web_sys::window()
+            .expect("couldn't get window handle")
+            .local_storage()
+            .expect("couldn't get local storage handle")
+            .expect_throw("local storage not supported?")
+    }
+}
+
+impl TodoStore for LocalStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        self.storage()
+            .get_item(key)
+            .expect_throw("error reading from storage")
+    }
+
+    fn save(&self, key: &str, data: &str) {
+        self.storage().set_item(key, data).unwrap_throw();
+    }
+
+    fn clear(&self, key: &str) {
+        self.storage().remove_item(key).unwrap_throw();
+    }
+}
+
+impl SideEffect<Message> for Command {
+    fn process(self, dispatcher: &Dispatcher<Message, Command>) {
+        use Command::*;
+
+        match self {
+            FocusPending => {
+                let pending_input = web_sys::window()
+                    .expect("couldn't get window handle")
+                    .document()
+                    .expect("couldn't get document handle")
+                    .query_selector("section.todoapp header.header input.new-todo")
+                    .expect("error querying for element")
+                    .expect("expected to find an input element")
+                    .dyn_into::<web_sys::HtmlInputElement>()
+                    .expect_throw("expected web_sys::HtmlInputElement");
+
+                pending_input.focus().expect_throw("error focusing input");
+            }
+            FocusEdit => {
+                let edit_input = web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .document()
+                    .expect_throw("couldn't get document handle")
+                    .query_selector("section.todoapp section.main input.edit")
+                    .expect_throw("error querying for element")
+                    .expect_throw("expected to find an input element")
+                    .dyn_into::<web_sys::HtmlInputElement>()
+                    .expect_throw("expected web_sys::HtmlInputElement");
+
+                edit_input.focus().expect_throw("error focusing input");
+            }
+            PushHistory(route) => {
+                let history = web_sys::window()
+                    .expect("couldn't get window handle")
+                    .history()
+                    .expect_throw("couldn't get history handle");
+
+                history.push_state_with_url(&history_state(&route), TITLE, Some(&route.to_url()))
+                    .expect_throw("error updating history");
+            }
+            ReplaceHistory(route) => {
+                let history = web_sys::window()
+                    .expect("couldn't get window handle")
+                    .history()
+                    .expect_throw("couldn't get history handle");
+
+                history.replace_state_with_url(&history_state(&route), TITLE, Some(&route.to_url()))
+                    .expect_throw("error updating history");
+            }
+            PopHistory => {
+                let history = web_sys::window()
+                    .expect("couldn't get window handle")
+                    .history()
+                    .expect_throw("couldn't get history handle");
+
+                history.back().expect_throw("error going back in history");
+            }
+            SetTitle(title) => {
+                let document = web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .document()
+                    .expect_throw("couldn't get document handle");
+
+                if document.title() != title {
+                    document.set_title(&title);
+                }
+            }
+            ScrollTo(id) => {
+                let item = web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .document()
+                    .expect_throw("couldn't get document handle")
+                    .query_selector(&format!("section.todoapp section.main li[data-id=\"{}\"]", id))
+                    .expect_throw("error querying for element");
+
+                // the item may be hidden by the current filter
+                if let Some(item) = item {
+                    item.scroll_into_view();
+                }
+            }
+            CopyLink(fragment) => {
+                let window = web_sys::window().expect_throw("couldn't get window handle");
+
+                let href = window.location().href().expect_throw("error reading location");
+                let url = match href.find('#') {
+                    Some(i) => format!("{}{}", &href[..i], fragment),
+                    None => format!("{}{}", href, fragment),
+                };
+
+                // `Clipboard` isn't available in older versions of web-sys
+                let clipboard = js_sys::Reflect::get(&window.navigator(), &JsValue::from_str("clipboard"))
+                    .ok()
+                    .filter(|clipboard| !clipboard.is_undefined());
+                let write_text = clipboard.as_ref()
+                    .and_then(|clipboard| js_sys::Reflect::get(clipboard, &JsValue::from_str("writeText")).ok())
+                    .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+
+                match (clipboard, write_text) {
+                    (Some(clipboard), Some(write_text)) => {
+                        if let Err(e) = write_text.call1(&clipboard, &JsValue::from_str(&url)) {
+                            error!("error copying link: {:?}", e);
+                        }
+                    }
+                    _ => error!("clipboard not available, couldn't copy {}", url),
+                }
+            }
+            UpdateStorage(store, key, data) => {
+                store.save(&key, &data);
+            }
+            ClearStorage(store, key) => {
+                store.clear(&key);
+            }
+            Download(format, data) => {
+                let document = web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .document()
+                    .expect_throw("couldn't get document handle");
+
+                let mut options = web_sys::BlobPropertyBag::new();
+                // `set_type` isn't available in older versions of web-sys
+                #[allow(deprecated)]
+                options.type_(format.mime_type());
+
+                let blob = web_sys::Blob::new_with_str_sequence_and_options(
+                    &js_sys::Array::of1(&JsValue::from_str(&data)),
+                    &options,
+                ).expect_throw("error creating blob");
+
+                let url = web_sys::Url::create_object_url_with_blob(&blob)
+                    .expect_throw("error creating object url");
+
+                let link = document.create_element("a")
+                    .expect_throw("error creating link")
+                    .dyn_into::<web_sys::HtmlAnchorElement>()
+                    .expect_throw("expected web_sys::HtmlAnchorElement");
+
+                link.set_href(&url);
+                link.set_download(format.file_name());
+                link.click();
+
+                web_sys::Url::revoke_object_url(&url).expect_throw("error revoking object url");
+            }
+            Upload => {
+                let input = web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .document()
+                    .expect_throw("couldn't get document handle")
+                    .create_element("input")
+                    .expect_throw("error creating input")
+                    .dyn_into::<web_sys::HtmlInputElement>()
+                    .expect_throw("expected web_sys::HtmlInputElement");
+
+                input.set_type("file");
+                input.set_accept(".json,.csv,.md,.markdown,.txt");
+
+                let dispatcher = dispatcher.clone();
+                let on_change = Closure::once_into_js(move |e: web_sys::Event| {
+                    let file = e.target()
+                        .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+                        .and_then(|input| input.files())
+                        .and_then(|files| files.get(0));
+
+                    let file = match file {
+                        Some(file) => file,
+                        None => return,
+                    };
+
+                    let name = file.name();
+                    let format = Format::from_file_name(&name).unwrap_or(Format::Json);
+                    let reader = web_sys::FileReader::new().expect_throw("error creating file reader");
+
+                    let on_load = {
+                        let reader = reader.clone();
+                        Closure::once_into_js(move || {
+                            match reader.result().ok().and_then(|data| data.as_string()) {
+                                Some(data) => dispatcher.dispatch(Message::Imported(format, data)),
+                                None => error!("error reading {}", name),
+                            }
+                        })
+                    };
+
+                    reader.set_onload(Some(on_load.unchecked_ref()));
+                    reader.read_as_text(&file).expect_throw("error reading file");
+                });
+
+                input.set_onchange(Some(on_change.unchecked_ref()));
+                input.click();
+            }
+        }
+    }
+}
+
+/// The state stored with a history entry, the filter and the url of the route it is for.
+fn history_state(route: &AppRoute) -> JsValue {
+    let state = js_sys::Object::new();
+    js_sys::Reflect::set(&state, &JsValue::from_str("filter"), &JsValue::from_str(route.filter.name()))
+        .expect_throw("error building history state");
+    js_sys::Reflect::set(&state, &JsValue::from_str("url"), &JsValue::from_str(&route.to_url()))
+        .expect_throw("error building history state");
+    state.into()
+}
+
+/// The route stored with a history entry by `history_state`, if it was added by the app.
+fn route_from_state(state: &JsValue) -> Option<AppRoute> {
+    let get = |key| js_sys::Reflect::get(state, &JsValue::from_str(key)).ok()?.as_string();
+    let mut route = AppRoute::parse(&get("url")?).ok()?;
+    route.filter = Filter::from_name(&get("filter")?)?;
+    Some(route)
+}
+
+#[derive(Default)]
+struct Router {}
+
+impl Route<Message> for Router {
+    fn route(&self, url: &str) -> Option<Message> {
+        match AppRoute::parse(url) {
+            Ok(route) => Some(Message::Navigate(route)),
+            Err(e) => {
+                warn!("unknown route {}: {}", url, e);
+                Some(Message::UnknownRoute(url.to_owned()))
+            }
+        }
+    }
+}
+
+/// Map a global keyboard shortcut to a message.
+fn shortcut(e: &web_sys::KeyboardEvent) -> Option<Message> {
+    let target = e.target().and_then(|t| t.dyn_into::<web_sys::Element>().ok());
+    let tag = target.as_ref().map(|t| t.tag_name()).unwrap_or_default();
+
+    // leave text entry to handle its own keys (including undo)
+    if target.as_ref().is_some_and(is_text_entry) {
+        return None;
+    }
+
+    if e.ctrl_key() || e.meta_key() {
+        return match e.key().as_ref() {
+            "z" | "Z" if e.shift_key() => Some(Message::Redo),
+            "z" | "Z" => Some(Message::Undo),
+            "y" | "Y" => Some(Message::Redo),
+            _ => None,
+        };
+    }
+
+    if e.alt_key() {
+        return None;
+    }
+
+    match e.key().as_ref() {
+        "j" | "ArrowDown" => Some(Message::CursorDown),
+        "k" | "ArrowUp" => Some(Message::CursorUp),
+        // let links, buttons and other controls handle their own activation
+        " " | "Enter" if matches!(tag.as_ref(), "A" | "BUTTON" | "INPUT" | "SELECT" | "SUMMARY" | "TEXTAREA") => None,
+        " " => Some(Message::ToggleAtCursor),
+        "e" | "Enter" => Some(Message::EditAtCursor),
+        "Delete" => Some(Message::RemoveAtCursor),
+        "/" => Some(Message::FocusNewTodo),
+        _ => None,
+    }
+}
+
+/// Check if an element takes typed text, as opposed to controls like checkboxes.
+fn is_text_entry(element: &web_sys::Element) -> bool {
+    match element.tag_name().as_ref() {
+        "TEXTAREA" | "SELECT" => true,
+        "INPUT" => match element.dyn_ref::<web_sys::HtmlInputElement>() {
+            Some(input) => !matches!(input.type_().as_ref(), "checkbox" | "radio" | "button" | "submit" | "reset" | "range" | "color" | "file"),
+            None => true,
+        },
+        _ => false,
+    }
+}
+
+/// Dispatch messages for global keyboard shortcuts.
+fn listen_for_shortcuts(dispatcher: Dispatcher<Message, Command>) {
+    let listener = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
+        if let Some(msg) = shortcut(&e) {
+            e.prevent_default();
+            dispatcher.dispatch(msg);
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    web_sys::window()
+        .expect("couldn't get window handle")
+        .add_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref())
+        .expect_throw("error adding keydown listener");
+
+    // the listener lives as long as the page
+    listener.forget();
+}
+
+/// Restore the route of history entries the app added when going back and forward to them.
+fn listen_for_history(dispatcher: Dispatcher<Message, Command>) {
+    let listener = Closure::wrap(Box::new(move |e: web_sys::PopStateEvent| {
+        // entries without our state, like a url typed in, are left to the router, which sees the
+        // url of the others as well, but navigating to the same route again changes nothing
+        if let Some(route) = route_from_state(&e.state()) {
+            dispatcher.dispatch(Message::Navigate(route));
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    web_sys::window()
+        .expect("couldn't get window handle")
+        .add_event_listener_with_callback("popstate", listener.as_ref().unchecked_ref())
+        .expect_throw("error adding popstate listener");
+
+    // the listener lives as long as the page
+    listener.forget();
+}
+
+/// Reload the items and lists when another tab changes them.
+fn listen_for_storage_changes(dispatcher: Dispatcher<Message, Command>) {
+    let listener = Closure::wrap(Box::new(move |e: web_sys::StorageEvent| {
+        let key = match e.key() {
+            Some(key) => key,
+            None => return,
+        };
+
+        if key == LISTS_KEY {
+            match e.new_value().map(|data| lists::decode(&data)) {
+                Some(Ok(lists)) => dispatcher.dispatch(Message::ListsReloaded(lists)),
+                Some(Err(e)) => error!("error reading lists changed in another tab: {}", e),
+                None => {}
+            }
+            return;
+        }
+
+        if key == SETTINGS_KEY {
+            match e.new_value().map(|data| settings::decode(&data)) {
+                Some(Ok(settings)) => dispatcher.dispatch(Message::SettingsReloaded(settings)),
+                Some(Err(e)) => error!("error reading settings changed in another tab: {}", e),
+                None => {}
+            }
+            return;
+        }
+
+        if !lists::is_list_key(&key) && !lists::is_archive_key(&key) && !lists::is_trash_key(&key) {
+            return;
+        }
+
+        let items = match e.new_value() {
+            Some(data) => match schema::decode(&data) {
+                Ok(items) => items,
+                Err(e) => {
+                    error!("error reading items changed in another tab: {}", e);
+                    return;
+                }
+            },
+            // storage was cleared
+            None => vec![],
+        };
+
+        dispatcher.dispatch(Message::ItemsReloaded(key, items));
+    }) as Box<dyn FnMut(_)>);
+
+    web_sys::window()
+        .expect("couldn't get window handle")
+        .add_event_listener_with_callback("storage", listener.as_ref().unchecked_ref())
+        .expect_throw("error adding storage listener");
+
+    // the listener lives as long as the page
+    listener.forget();
+}
+
+#[wasm_bindgen(start)]
+pub fn main() -> Result<(), JsValue> {
+    init_log();
+    set_panic_hook();
+
+    let parent = web_sys::window()
+        .expect("couldn't get window handle")
+        .document()
+        .expect("couldn't get document handle")
+        .query_selector("section.todoapp")
+        .expect("error querying for element")
+        .expect("expected <section class=\"todoapp\"></section>");
+
+    // choose where todos are persisted
+    let store: Rc<dyn TodoStore> = Rc::new(LocalStorage::default());
+
+    let app = AppBuilder::default()
+        .router(Router::default())
+        .attach(parent, Todo::with_store(store));
+
+    let dispatcher: Dispatcher<Message, Command> = app.into();
+    listen_for_shortcuts(dispatcher.clone());
+    listen_for_history(dispatcher.clone());
+    listen_for_storage_changes(dispatcher.clone());
+
+    if let Some(days) = AUTO_ARCHIVE_DAYS {
+        dispatcher.dispatch(Message::AutoArchive(days));
+    }
+    if let Some(days) = TRASH_DAYS {
+        dispatcher.dispatch(Message::PurgeTrash(days));
+    }
+
+    Command::FocusPending.process(&dispatcher);
+
+    info!("{} initialized", TITLE);
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        use crate::todo::{Filter,SortMode};
+
+        let router = Router::default();
+        let route = |filter, sort| Some(Message::Navigate(AppRoute { filter, sort, .. AppRoute::default() }));
+
+        assert_eq!(router.route("http://localhost:8080"), route(Filter::All, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/"), route(Filter::All, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/"), route(Filter::All, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/active"), route(Filter::Active, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/completed"), route(Filter::Completed, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/due"), route(Filter::Due, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/active?sort=priority"), route(Filter::Active, SortMode::Priority));
+        assert_eq!(router.route("http://localhost:8080/#/tag/work/completed"), Some(Message::Navigate(AppRoute {
+            filter: Filter::Completed,
+            tag: Some("work".to_owned()),
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/list/home/active"), Some(Message::Navigate(AppRoute {
+            list: Some("home".to_owned()),
+            filter: Filter::Active,
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/active?q=milk"), Some(Message::Navigate(AppRoute {
+            filter: Filter::Active,
+            search: "milk".to_owned(),
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/item/3/"), Some(Message::Navigate(AppRoute {
+            item: Some(3),
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/item/3/edit"), Some(Message::Navigate(AppRoute {
+            item: Some(3),
+            edit: true,
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/active?utm_source=x"), route(Filter::Active, SortMode::Insertion));
+        assert_eq!(router.route("http://localhost:8080/#/list/home/archive"), Some(Message::Navigate(AppRoute {
+            list: Some("home".to_owned()),
+            view: crate::todo::View::Archive,
+            .. AppRoute::default()
+        })));
+        assert_eq!(router.route("http://localhost:8080/#/trash"), Some(Message::Navigate(AppRoute {
+            view: crate::todo::View::Trash,
+            .. AppRoute::default()
+        })));
+
+        // unknown routes are reported instead of silently showing everything
+        for url in &[
+            "http://localhost:8080/#/bogus",
+            "http://localhost:8080/#/active/completed",
+            "http://localhost:8080/#/list",
+            "http://localhost:8080/#/item/x",
+            "http://localhost:8080/#/?sort=bogus",
+        ] {
+            assert_eq!(router.route(url), Some(Message::UnknownRoute(url.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_route_round_trip() {
+        use crate::todo::{Filter,SortMode,View};
+
+        // a small deterministic generator, so failures can be reproduced
+        let mut seed = 0x2545_f491_u64;
+        let mut next = move |n: usize| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % n
+        };
+        let chars = ['a', 'Z', '0', ' ', '/', '%', '?', '&', '=', '#', '+', '~', '.', 'é', '✓'];
+        let text = |next: &mut dyn FnMut(usize) -> usize| {
+            let len = next(6);
+            (0..len).map(|_| chars[next(chars.len())]).collect::<String>()
+        };
+
+        let router = Router::default();
+        for _ in 0..500 {
+            let list = text(&mut next);
+            let search = text(&mut next);
+            let item = if next(2) == 0 { None } else { Some(next(1000) as todo::ItemId) };
+            let route = AppRoute {
+                list: if list.is_empty() { None } else { Some(list) },
+                view: [View::Items, View::Archive, View::Trash][next(3)],
+                filter: Filter::ALL[next(Filter::ALL.len())],
+                item,
+                edit: item.is_some() && next(2) == 0,
+                sort: SortMode::ALL[next(SortMode::ALL.len())],
+                tag: [None, Some("work"), Some("q3-plan_b")][next(3)].map(str::to_owned),
+                search,
+            };
+
+            let url = format!("http://localhost:8080/{}", route.to_url());
+            assert_eq!(router.route(&url), Some(Message::Navigate(route)), "{}", url);
+        }
+    }
+}
@@ -0,0 +1,883 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use euca::app::Render;
+use euca::dom;
+
+use std::ops::Range;
+use chrono::{NaiveDate,NaiveTime};
+
+use crate::todo::{Todo,Item,ItemId,Message,Command,Filter,SortMode,View,AppRoute,Recurrence,parse_lines};
+use crate::todo::export::Format;
+use crate::todo::clock;
+
+impl Render<dom::DomVec<Message, Command>> for Todo {
+    fn render(&self) -> dom::DomVec<Message, Command> {
+        use dom::Dom;
+        use dom::Handler::Event;
+
+        let mut vec = vec![];
+        vec.push(Dom::elem("header")
+            .attr("class", "header")
+            .push(Dom::elem("h1").push("todos"))
+            .push(self.render_lists())
+            .push(self.render_settings())
+            .push(Dom::elem("input")
+                .attr("class", "search")
+                .attr("type", "search")
+                .attr("placeholder", "Search")
+                .attr("value", self.search.to_owned())
+                .on("input", dom::Handler::InputValue(|s| {
+                    Some(Message::UpdateSearch(s))
+                }))
+            )
+            .push(Dom::elem("input")
+                .attr("class", "new-todo")
+                .attr("placeholder", "What needs to be done?")
+                .attr("autofocus", "true")
+                .attr("value", self.pending_item.to_owned())
+                .on("input", dom::Handler::InputValue(|s| {
+                    Some(Message::UpdatePending(s))
+                }))
+                .on("keyup", Event(|e| {
+                    let e = e.dyn_into::<web_sys::KeyboardEvent>().expect_throw("expected web_sys::KeyboardEvent");
+                    match e.key().as_ref() {
+                        "Enter" => Some(Message::AddTodo),
+                        _ => None,
+                    }
+                }))
+                .on("paste", Event(|e| {
+                    let text = e.dyn_ref::<web_sys::ClipboardEvent>()?
+                        .clipboard_data()?
+                        .get_data("text/plain").ok()?;
+
+                    // a single line is pasted into the input as usual
+                    if !text.trim().contains('\n') {
+                        return None;
+                    }
+
+                    e.prevent_default();
+                    Some(Message::AddTodos(parse_lines(&text)))
+                }))
+            )
+            .extend(self.render_quick_add_preview())
+        );
+
+        // render todo list if necessary
+        // XXX use css visibility here?
+        if self.view != View::Items {
+            vec.push(match self.view {
+                View::Trash => self.render_trash(),
+                _ => self.render_archive(),
+            });
+
+            let footer = Dom::elem("footer")
+                .attr("class", "footer")
+                .push(self.render_view_link(View::Items, "Back to list".to_owned()));
+
+            vec.push(match self.render_toast() {
+                Some(toast) => footer.push(toast),
+                None => footer,
+            });
+        }
+        else if !self.items.is_empty() {
+            // main section
+            vec.push(Dom::elem("section")
+                .attr("class", "main")
+                .push(Dom::elem("input")
+                    .attr("id", "toggle-all")
+                    .attr("class", "toggle-all")
+                    .attr("type", "checkbox")
+                    .attr("checked", self.items.iter().all(|item| item.is_complete).to_string())
+                    .event("change", Message::ToggleAll)
+                )
+                .push(Dom::elem("label")
+                    .attr("for", "toggle-all")
+                    .push("Mark all as complete")
+                )
+                .push(Dom::elem("ul")
+                    .attr("class", "todo-list")
+                    .extend(self.visible_items()
+                        .into_iter()
+                        .map(|item| item.render(self))
+                    )
+                )
+            );
+
+            // todo footer
+            vec.push({
+                let remaining = self.items.iter()
+                    .filter(|item| !item.is_complete)
+                    .count();
+
+                let footer = Dom::elem("footer")
+                    .attr("class", "footer")
+                    .push(Dom::elem("span")
+                        .attr("class", "todo-count")
+                        .push(Dom::elem("strong")
+                            .push(remaining.to_string())
+                        )
+                        .push(
+                            if remaining == 1 { " item left" }
+                            else { " items left" }
+                        )
+                    )
+                    .push(Dom::elem("ul")
+                        .attr("class", "filters")
+                        .push(Dom::elem("li")
+                            .push(Dom::elem("a")
+                                .attr("href", self.filter_url(Filter::All))
+                                .attr("class",
+                                    if self.filter == Filter::All { "selected" }
+                                    else { "" }
+                                 )
+                                .push("All")
+                                .on("click", Event(|e| {
+                                    e.prevent_default();
+                                    Some(Message::ShowAll(true))
+                                }))
+                            )
+                        )
+                        .push(Dom::elem("li")
+                            .push(Dom::elem("a")
+                                .attr("href", self.filter_url(Filter::Active))
+                                .attr("class",
+                                    if self.filter == Filter::Active { "selected" }
+                                    else { "" }
+                                 )
+                                .push("Active")
+                                .on("click", Event(|e| {
+                                    e.prevent_default();
+                                    Some(Message::ShowActive(true))
+                                }))
+                            )
+                        )
+                        .push(Dom::elem("li")
+                            .push(Dom::elem("a")
+                                .attr("href", self.filter_url(Filter::Completed))
+                                .attr("class",
+                                    if self.filter == Filter::Completed { "selected" }
+                                    else { "" }
+                                 )
+                                .push("Completed")
+                                .on("click", Event(|e| {
+                                    e.prevent_default();
+                                    Some(Message::ShowCompleted(true))
+                                }))
+                            )
+                        )
+                        .push(Dom::elem("li")
+                            .push(Dom::elem("a")
+                                .attr("href", self.filter_url(Filter::Due))
+                                .attr("class",
+                                    if self.filter == Filter::Due { "selected" }
+                                    else { "" }
+                                 )
+                                .push("Due")
+                                .on("click", Event(|e| {
+                                    e.prevent_default();
+                                    Some(Message::ShowDue(true))
+                                }))
+                            )
+                        )
+                    )
+                    .push(self.render_sort())
+                ;
+                let footer = match self.tag {
+                    Some(ref tag) => footer.push(Dom::elem("div")
+                        .attr("class", "tag-filter")
+                        .push(format!("Tagged #{} ", tag))
+                        .push(Dom::elem("button")
+                            .attr("class", "clear-tag")
+                            .attr("title", "Show all tags")
+                            .push("×")
+                            .event("click", Message::ShowTag(None))
+                        )
+                    ),
+                    None => footer,
+                };
+                let footer = if self.items.iter().any(|item| item.is_complete) {
+                    footer.push(Dom::elem("button")
+                        .attr("class", "clear-completed")
+                        .push("Clear completed")
+                        .event("click", Message::ClearCompleted)
+                    )
+                }
+                else {
+                    footer
+                };
+
+                let footer = footer
+                    .extend(self.render_archive_link())
+                    .extend(self.render_trash_link())
+                    .push(self.render_transfer());
+
+                match self.render_toast() {
+                    Some(toast) => footer.push(toast),
+                    None => footer,
+                }
+            });
+        }
+        else {
+            // keep importing possible and the undo toast around even if the list is empty
+            let footer = Dom::elem("footer")
+                .attr("class", "footer")
+                .extend(self.render_archive_link())
+                .extend(self.render_trash_link())
+                .push(self.render_transfer());
+
+            vec.push(match self.render_toast() {
+                Some(toast) => footer.push(toast),
+                None => footer,
+            });
+        }
+
+        vec.into()
+    }
+}
+
+impl Todo {
+    /// The url for a filter, keeping the current sort order.
+    fn filter_url(&self, filter: Filter) -> String {
+        AppRoute {
+            filter,
+            item: None,
+            edit: false,
+            .. self.route()
+        }.to_url()
+    }
+
+    /// Render the controls for picking, creating, renaming and deleting lists.
+    fn render_lists(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+        use dom::Handler::Event;
+
+        let current = &self.current_list().name;
+
+        let e = Dom::elem("div")
+            .attr("class", "lists")
+            .push(Dom::elem("select")
+                .attr("class", "list-picker")
+                .attr("title", "Switch list")
+                .extend(self.lists.iter().map(|list| {
+                    let option = Dom::elem("option")
+                        .attr("value", list.name.to_owned())
+                        .push(list.name.to_owned());
+
+                    if &list.name == current {
+                        option.attr("selected", "true")
+                    }
+                    else {
+                        option
+                    }
+                }))
+                .on("change", Event(|e| {
+                    let select = e.target()?.dyn_into::<web_sys::HtmlSelectElement>().ok()?;
+                    Some(Message::SwitchList(select.value()))
+                }))
+            )
+            .push(Dom::elem("input")
+                .attr("class", "list-name")
+                .attr("title", "Rename list")
+                .attr("data-list", current.to_owned())
+                .attr("value", current.to_owned())
+                .on("change", Event(|e| {
+                    let input = e.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                    let from = input.get_attribute("data-list")?;
+                    Some(Message::RenameList(from, input.value()))
+                }))
+            );
+
+        let e = if self.lists.len() > 1 {
+            e.push(Dom::elem("button")
+                .attr("class", "delete-list")
+                .attr("title", format!("Delete the {} list", current))
+                .push("Delete list")
+                .event("click", Message::DeleteList(current.to_owned()))
+            )
+        }
+        else {
+            e
+        };
+
+        e.push(Dom::elem("input")
+            .attr("class", "new-list")
+            .attr("placeholder", "New list")
+            .on("keyup", Event(|e| {
+                let key = e.dyn_ref::<web_sys::KeyboardEvent>()?.key();
+                if key != "Enter" {
+                    return None;
+                }
+
+                let input = e.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                let name = input.value();
+                input.set_value("");
+                Some(Message::CreateList(name))
+            }))
+        )
+    }
+
+    /// Render the sort order picker.
+    fn render_sort(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+        use dom::Handler::Event;
+
+        Dom::elem("select")
+            .attr("class", "sort")
+            .attr("title", "Sort by")
+            .extend(SortMode::ALL.iter().map(|&sort| {
+                let option = Dom::elem("option")
+                    .attr("value", sort.name())
+                    .push(sort.name());
+
+                if sort == self.sort {
+                    option.attr("selected", "true")
+                }
+                else {
+                    option
+                }
+            }))
+            .on("change", Event(|e| {
+                let select = e.target()?.dyn_into::<web_sys::HtmlSelectElement>().ok()?;
+                SortMode::from_name(&select.value()).map(Message::SortBy)
+            }))
+    }
+
+    fn render_toast(&self) -> Option<dom::Dom<Message, Command>> {
+        use dom::Dom;
+
+        self.toast.as_ref().map(|toast| {
+            let e = Dom::elem("div")
+                .attr("class", "toast")
+                .push(Dom::elem("span")
+                    .push(toast.message.to_owned())
+                );
+
+            let e = if toast.undoable {
+                e.push(Dom::elem("button")
+                    .attr("class", "undo")
+                    .push("Undo")
+                    .event("click", Message::Undo)
+                )
+            }
+            else {
+                e
+            };
+
+            e.push(Dom::elem("button")
+                .attr("class", "dismiss")
+                .push("×")
+                .event("click", Message::DismissToast)
+            )
+        })
+    }
+
+    /// Render a link switching to the given view.
+    fn render_view_link(&self, view: View, text: String) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+
+        let url = AppRoute {
+            view,
+            item: None,
+            edit: false,
+            .. self.route()
+        }.to_url();
+
+        Dom::elem("a")
+            .attr("class", "view-link")
+            .attr("href", url)
+            .push(text)
+            .on("click", dom::Handler::Event(|e| {
+                e.prevent_default();
+                // handlers can't capture the view, read it back from the link
+                let href = e.target()?.dyn_into::<web_sys::Element>().ok()?.get_attribute("href")?;
+                AppRoute::parse(&href).ok().map(|route| Message::ShowView(route.view))
+            }))
+    }
+
+    /// Render a link to the archive, if anything has been archived.
+    fn render_archive_link(&self) -> Option<dom::Dom<Message, Command>> {
+        if self.archive.is_empty() {
+            return None;
+        }
+
+        Some(self.render_view_link(View::Archive, format!("Archive ({})", self.archive.len())))
+    }
+
+    /// Render the controls for the settings shared by all lists.
+    fn render_settings(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+
+        Dom::elem("details")
+            .attr("class", "settings")
+            .push(Dom::elem("summary").push("Settings"))
+            .push(Dom::elem("label")
+                .push(Dom::elem("input")
+                    .attr("type", "checkbox")
+                    .attr("class", "complete-children")
+                    .attr("checked", self.settings.complete_children.to_string())
+                    .event("change", Message::SetCompleteChildren(!self.settings.complete_children))
+                )
+                .push("Completing an item completes its subtasks")
+            )
+            .push(Dom::elem("label")
+                .push(Dom::elem("input")
+                    .attr("type", "checkbox")
+                    .attr("class", "fuzzy-search")
+                    .attr("checked", self.settings.fuzzy_search.to_string())
+                    .event("change", Message::SetFuzzySearch(!self.settings.fuzzy_search))
+                )
+                .push("Search for letters in order, not just whole words")
+            )
+    }
+
+    /// Render what the quick add syntax makes of the item being typed, if anything.
+    fn render_quick_add_preview(&self) -> Option<dom::Dom<Message, Command>> {
+        use dom::Dom;
+
+        let parts = self.pending_quick_add().preview(self.today());
+        if parts.is_empty() {
+            return None;
+        }
+
+        Some(Dom::elem("div")
+            .attr("class", "quick-add-preview")
+            .extend(parts.into_iter().map(|part| {
+                Dom::elem("span")
+                    .attr("class", "quick-add-field")
+                    .push(part)
+            }))
+        )
+    }
+
+    /// Render a link to the trash, if anything is in it.
+    fn render_trash_link(&self) -> Option<dom::Dom<Message, Command>> {
+        if self.trash.is_empty() {
+            return None;
+        }
+
+        Some(self.render_view_link(View::Trash, format!("Trash ({})", self.trash.len())))
+    }
+
+    /// Render the items in the trash, most recently removed first.
+    fn render_trash(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+
+        let section = Dom::elem("section")
+            .attr("class", "main trash")
+            .push(Dom::elem("h2").push("Trash"));
+
+        if self.trash.is_empty() {
+            return section.push(Dom::elem("p")
+                .attr("class", "trash-empty")
+                .push("The trash is empty. Deleted items are kept here for a while.")
+            );
+        }
+
+        let now = self.now();
+        section
+            .push(Dom::elem("button")
+                .attr("class", "empty-trash")
+                .push("Empty trash")
+                .event("click", Message::EmptyTrash)
+            )
+            .push(Dom::elem("ul")
+                .attr("class", "todo-list")
+                .extend(self.trash.iter().rev().map(|item| {
+                    let deleted = item.deleted_at
+                        .map(|t| format!("Deleted {}", clock::ago(t, now)))
+                        .unwrap_or_default();
+
+                    let e = Dom::elem("li")
+                        .attr("data-id", item.id.to_string());
+                    let e = if item.is_complete {
+                        e.attr("class", "completed")
+                    }
+                    else {
+                        e
+                    };
+
+                    e.push(Dom::elem("div")
+                        .attr("class", "view")
+                        .push(Dom::elem("label")
+                            .attr("title", deleted)
+                            .push(item.text_with_tags())
+                        )
+                        .push(Dom::elem("button")
+                            .attr("class", "restore")
+                            .attr("title", "Restore")
+                            .push("Restore")
+                            .event("click", Message::RestoreTrashed(item.id))
+                        )
+                    )
+                }))
+            )
+    }
+
+    /// Render the archived items, grouped by when they were completed.
+    fn render_archive(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+
+        let section = Dom::elem("section")
+            .attr("class", "main archive")
+            .push(Dom::elem("h2").push("Archive"));
+
+        if self.archive.is_empty() {
+            return section.push(Dom::elem("p")
+                .attr("class", "archive-empty")
+                .push("Nothing has been archived yet. Clearing completed items moves them here.")
+            );
+        }
+
+        let now = self.now();
+        section.extend(self.archive_by_date().into_iter().map(|(date, items)| {
+            Dom::elem("div")
+                .attr("class", "archive-group")
+                .push(Dom::elem("h3")
+                    .push(match date {
+                        Some(date) => format!("Completed {}", date.format("%A, %B %-d, %Y")),
+                        None => "Completed earlier".to_owned(),
+                    })
+                )
+                .push(Dom::elem("ul")
+                    .attr("class", "todo-list")
+                    .extend(items.into_iter().map(|item| {
+                        Dom::elem("li")
+                            .attr("data-id", item.id.to_string())
+                            .attr("class", "completed")
+                            .push(Dom::elem("div")
+                                .attr("class", "view")
+                                .push(Dom::elem("label")
+                                    .attr("title", item.activity(now))
+                                    .push(item.text_with_tags())
+                                )
+                                .push(Dom::elem("button")
+                                    .attr("class", "restore")
+                                    .attr("title", "Restore")
+                                    .push("Restore")
+                                    .event("click", Message::RestoreArchived(item.id))
+                                )
+                                .push(Dom::elem("button")
+                                    .attr("class", "destroy")
+                                    .attr("title", "Delete permanently")
+                                    .event("click", Message::DeleteArchived(item.id))
+                                )
+                            )
+                    }))
+                )
+        }))
+    }
+
+    /// Render the export and import controls.
+    fn render_transfer(&self) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+
+        let e = Dom::elem("div")
+            .attr("class", "transfer");
+
+        let e = if self.items.is_empty() {
+            e
+        }
+        else {
+            e.push(Dom::elem("span").push("Export: "))
+                .extend(Format::ALL.iter().map(|&format| {
+                    Dom::elem("button")
+                        .attr("class", "export")
+                        .attr("title", format!("Download as {}", format.file_name()))
+                        .push(format.name())
+                        .event("click", Message::Export(format))
+                }))
+        };
+
+        e.push(Dom::elem("button")
+            .attr("class", "import")
+            .attr("title", "Add todos from a JSON, CSV, Markdown or todo.txt file")
+            .push("Import…")
+            .event("click", Message::Import)
+        )
+    }
+}
+
+/// Find the id of the item an event happened in.
+fn event_item_id(e: &web_sys::Event) -> Option<ItemId> {
+    e.target()?
+        .dyn_into::<web_sys::Element>().ok()?
+        .closest("li[data-id]").ok()??
+        .get_attribute("data-id")?
+        .parse().ok()
+}
+
+/// Split text into plain and `mark`ed parts for the given ranges.
+fn highlight(text: &str, ranges: Vec<Range<usize>>) -> Vec<dom::Dom<Message, Command>> {
+    use dom::Dom;
+
+    let mut parts = vec![];
+    let mut pos = 0;
+    for range in ranges {
+        if range.start > pos {
+            parts.push(text[pos..range.start].into());
+        }
+        parts.push(Dom::elem("mark").push(&text[range.clone()]));
+        pos = range.end;
+    }
+    if pos < text.len() || parts.is_empty() {
+        parts.push(text[pos..].into());
+    }
+
+    parts
+}
+
+/// Get the value of the input an event happened in.
+fn event_input_value(e: &web_sys::Event) -> Option<String> {
+    e.target()?
+        .dyn_into::<web_sys::HtmlInputElement>().ok()
+        .map(|input| input.value())
+}
+
+impl Item {
+    /// Render the picker for how often this item recurs.
+    fn render_recurrence(&self, today: NaiveDate) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+        use dom::Handler::Event;
+
+        let option = |value: String, text: String, selected: bool| {
+            let option = Dom::elem("option")
+                .attr("value", value)
+                .push(text);
+
+            if selected {
+                option.attr("selected", "true")
+            }
+            else {
+                option
+            }
+        };
+
+        // keep rules that aren't offered, like every few days, selectable
+        let mut rules: Vec<Recurrence> = Recurrence::common(self.due.unwrap_or(today)).collect();
+        if let Some(recurrence) = self.recurrence.filter(|recurrence| !rules.contains(recurrence)) {
+            rules.push(recurrence);
+        }
+
+        Dom::elem("select")
+            .attr("class", "recurrence")
+            .attr("title", "Repeat")
+            .push(option(String::new(), "Doesn't repeat".to_owned(), self.recurrence.is_none()))
+            .extend(rules.into_iter().map(|recurrence| {
+                option(recurrence.to_string(), recurrence.describe(), self.recurrence == Some(recurrence))
+            }))
+            .on("change", Event(|e| {
+                let id = event_item_id(&e)?;
+                let select = e.target()?.dyn_into::<web_sys::HtmlSelectElement>().ok()?;
+                Some(Message::SetRecurrence(id, select.value().parse().ok()))
+            }))
+    }
+
+    fn render(&self, todo: &Todo) -> dom::Dom<Message, Command> {
+        use dom::Dom;
+        use dom::Handler::{Event,InputValue};
+
+        let e = Dom::elem("li")
+            .attr("data-id", self.id.to_string())
+            .on("dragstart", Event(|e| {
+                // don't let parent items replace the dragged subtask
+                e.stop_propagation();
+                // selecting text in an input isn't a drag
+                let target = e.target()?.dyn_into::<web_sys::Element>().ok()?;
+                if target.tag_name() == "INPUT" {
+                    return None;
+                }
+                let id = event_item_id(&e)?;
+                let data = e.dyn_ref::<web_sys::DragEvent>()?.data_transfer()?;
+                data.set_data("text/plain", &id.to_string()).ok()?;
+                data.set_effect_allowed("move");
+                None
+            }))
+            .on("dragover", Event(|e| {
+                // allow dropping
+                e.prevent_default();
+                None
+            }))
+            .on("drop", Event(|e| {
+                e.prevent_default();
+                e.stop_propagation();
+                let from = e.dyn_ref::<web_sys::DragEvent>()?
+                    .data_transfer()?
+                    .get_data("text/plain").ok()?
+                    .parse().ok()?;
+                let to = event_item_id(&e)?;
+                Some(Message::MoveTodo { from, to })
+            }))
+            .on("keydown", Event(|e| {
+                let e = e.dyn_into::<web_sys::KeyboardEvent>().ok()?;
+                if !e.alt_key() {
+                    return None;
+                }
+
+                let li = e.target()?
+                    .dyn_into::<web_sys::Element>().ok()?
+                    .closest("li[data-id]").ok()??;
+
+                // the next or previous visible item with the same parent
+                let sibling = match e.key().as_ref() {
+                    "ArrowUp" => li.previous_element_sibling()?,
+                    "ArrowDown" => li.next_element_sibling()?,
+                    _ => return None,
+                };
+
+                e.prevent_default();
+                e.stop_propagation();
+                Some(Message::MoveTodo {
+                    from: li.get_attribute("data-id")?.parse().ok()?,
+                    to: sibling.get_attribute("data-id")?.parse().ok()?,
+                })
+            }));
+
+        let pending_edit = match todo.pending_edit {
+            Some((id, ref pending_edit)) if id == self.id => Some(pending_edit),
+            Some(_) | None => None,
+        };
+
+        let e = if let Some(pending_edit) = pending_edit {
+            e.attr("class", "editing")
+                .push(Dom::elem("input")
+                    .attr("class", "edit")
+                    .attr("value", pending_edit.to_owned())
+                    .on("input", InputValue(|s| {
+                        Some(Message::UpdateEdit(s))
+                    }))
+                    .event("blur", Message::SaveEdit)
+                    .on("keyup", Event(|e| {
+                        let e = e.dyn_into::<web_sys::KeyboardEvent>().expect_throw("expected web_sys::KeyboardEvent");
+                        match e.key().as_ref() {
+                            "Enter" => Some(Message::SaveEdit),
+                            "Escape" => Some(Message::AbortEdit),
+                            _ => None,
+                        }
+                    }))
+                )
+        }
+        else {
+            let e = e.push(
+                Dom::elem("div")
+                    .attr("class", "view")
+                    // only the handle is draggable, so text in the inputs of the item can still
+                    // be selected
+                    .push(Dom::elem("span")
+                        .attr("class", "drag-handle")
+                        .attr("draggable", "true")
+                        .attr("title", "Drag to reorder")
+                        .push("⠿")
+                    )
+                    .push(Dom::elem("input")
+                        .attr("class", "toggle")
+                        .attr("type", "checkbox")
+                        .attr("checked", self.is_complete.to_string())
+                        .event("change", Message::ToggleTodo(self.id))
+                    )
+                    .push(Dom::elem("label")
+                        .attr("title", self.activity(todo.now()))
+                        .extend(highlight(&self.text, todo.search_matches(&self.text).unwrap_or_default()))
+                        .extend(self.progress().map(|(complete, total)| {
+                            Dom::elem("span")
+                                .attr("class", "progress")
+                                .attr("title", format!("{} of {} subtasks done", complete, total))
+                                .push(format!("{}/{}", complete, total))
+                        }))
+                        .extend(self.tags.iter().map(|tag| {
+                            Dom::elem("button")
+                                .attr("class", "tag")
+                                .attr("title", format!("Show items tagged #{}", tag))
+                                .push(format!("#{}", tag))
+                                .event("click", Message::ShowTag(Some(tag.to_owned())))
+                        }))
+                        .event("dblclick", Message::EditTodo(self.id))
+                    )
+                    .push(Dom::elem("button")
+                        .attr("class", format!("priority priority-{}", self.priority.name()))
+                        .attr("title", format!("Priority: {} (click to change)", self.priority.name()))
+                        .event("click", Message::CyclePriority(self.id))
+                    )
+                    .push(Dom::elem("input")
+                        .attr("class", "due")
+                        .attr("type", "date")
+                        .attr("title", "Due date")
+                        .attr("value", self.due.map(|due| due.to_string()).unwrap_or_default())
+                        .on("change", Event(|e| {
+                            let id = event_item_id(&e)?;
+                            let due = NaiveDate::parse_from_str(&event_input_value(&e)?, "%Y-%m-%d").ok();
+                            Some(Message::SetDue(id, due))
+                        }))
+                    )
+                    .extend(self.due.map(|_| {
+                        Dom::elem("input")
+                            .attr("class", "due-time")
+                            .attr("type", "time")
+                            .attr("title", "Due time")
+                            .attr("value", self.due_time.map(|time| time.format("%H:%M").to_string()).unwrap_or_default())
+                            .on("change", Event(|e| {
+                                let id = event_item_id(&e)?;
+                                let time = NaiveTime::parse_from_str(&event_input_value(&e)?, "%H:%M").ok();
+                                Some(Message::SetDueTime(id, time))
+                            }))
+                    }))
+                    .push(self.render_recurrence(todo.today()))
+                    .push(Dom::elem("button")
+                        .attr("class", "copy-link")
+                        .attr("title", "Copy link")
+                        .event("click", Message::CopyLink(self.id))
+                    )
+                    .push(Dom::elem("button")
+                        .attr("class", "destroy")
+                        .attr("title", "Move to trash")
+                        .event("click", Message::RemoveTodo(self.id))
+                    )
+            );
+
+            let mut classes = vec![];
+            if self.is_complete {
+                classes.push("completed");
+            }
+            if self.is_overdue(todo.today()) {
+                classes.push("overdue");
+            }
+            if todo.cursor == Some(self.id) {
+                classes.push("focused");
+            }
+
+            if classes.is_empty() {
+                e
+            }
+            else {
+                e.attr("class", classes.join(" "))
+            }
+        };
+
+        let children = todo.visible_children(self);
+        let e = if children.is_empty() {
+            e
+        }
+        else {
+            e.push(Dom::elem("ul")
+                .attr("class", "subtasks")
+                .extend(children.into_iter().map(|child| child.render(todo)))
+            )
+        };
+
+        e.push(Dom::elem("input")
+            .attr("class", "new-subtask")
+            .attr("placeholder", "Add a subtask")
+            .on("keyup", Event(|e| {
+                let key = e.dyn_ref::<web_sys::KeyboardEvent>()?.key();
+                if key != "Enter" {
+                    return None;
+                }
+
+                let id = event_item_id(&e)?;
+                let input = e.target()?.dyn_into::<web_sys::HtmlInputElement>().ok()?;
+                let text = input.value();
+                input.set_value("");
+                Some(Message::AddSubtask(id, text))
+            }))
+        )
+    }
+}